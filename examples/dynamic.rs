@@ -0,0 +1,42 @@
+extern crate clap;
+extern crate clap_generate;
+
+use std::io;
+
+use clap::{App, Arg};
+use clap_generate::{generate_dynamic_shim, Candidate, DynamicCompleter, Shell};
+
+fn build_cli() -> App<'static, 'static> {
+    App::new("dynamic")
+        .about("Completes values which are only known at runtime")
+        .arg(
+            Arg::with_name("shim")
+                .long("shim")
+                .possible_values(&Shell::variants())
+                .help("Prints the completion shim for a shell"),
+        )
+        .subcommand(
+            App::new("checkout")
+                .about("Switches branches")
+                .arg(Arg::with_name("branch").help("The branch to switch to")),
+        )
+}
+
+fn main() {
+    let completer = DynamicCompleter::from_app(&mut build_cli(), "dynamic")
+        .unwrap()
+        .values_for("checkout", "branch", |_| {
+            vec![
+                Candidate::new("master").help("The default branch"),
+                Candidate::new("develop"),
+            ]
+        });
+    if completer.complete_from_env().unwrap() {
+        return;
+    }
+
+    let m = build_cli().get_matches();
+    if let Some(shell) = m.value_of("shim") {
+        generate_dynamic_shim("dynamic", shell.parse().unwrap(), &mut io::stdout()).unwrap();
+    }
+}
@@ -0,0 +1,252 @@
+// Copyright ⓒ 2015-2018 Kevin B. Knapp
+//
+// `clap_generate` is distributed under the terms of both the MIT license and the Apache License
+// (Version 2.0).
+// See the [LICENSE-APACHE](LICENSE-APACHE) and [LICENSE-MIT](LICENSE-MIT) files in this repository
+// for more information.
+
+//! Generates completion scripts and man pages from a JSON or YAML command spec, for packaging
+//! pipelines which run outside of Cargo build scripts.
+//!
+//! ```shell
+//! $ clap-generate --all-shells --man --out-dir target/completions myapp.json
+//! $ clap-generate --shell bash --bin-name mt myapp.yaml > /usr/share/bash-completion/completions/mt
+//! ```
+
+#[macro_use]
+extern crate clap;
+extern crate clap_generate;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+
+use clap::{App, Arg, ArgMatches};
+use clap_generate::{gen_all_from_spec, gen_manuals_from_spec, generate_completions_from_spec,
+                    generate_from_spec_with, CarapaceGen, CommandSpec, FigGen, GenOptions, Shell};
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+fn build_cli() -> App<'static, 'static> {
+    App::new("clap-generate")
+        .version(crate_version!())
+        .about("Generates shell completion scripts and man pages from a command spec")
+        .arg(
+            Arg::with_name("spec")
+                .required(true)
+                .help("The JSON or YAML command spec to read, or '-' for stdin"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["json", "yaml"])
+                .help("The format of the spec [default: guessed from the file extension]"),
+        )
+        .arg(
+            Arg::with_name("shell")
+                .short('s')
+                .long("shell")
+                .takes_value(true)
+                .value_name("SHELL")
+                .multiple_occurrences(true)
+                .possible_values(&Shell::variants())
+                .help("The shell to generate completions for"),
+        )
+        .arg(
+            Arg::with_name("all-shells")
+                .short('a')
+                .long("all-shells")
+                .conflicts_with("shell")
+                .help("Generate completions for all supported shells"),
+        )
+        .arg(
+            Arg::with_name("fig")
+                .long("fig")
+                .help("Generate a completion spec for Fig (Amazon Q) and inshellisense"),
+        )
+        .arg(
+            Arg::with_name("carapace")
+                .long("carapace")
+                .help("Generate a carapace spec, which completes in every shell carapace supports"),
+        )
+        .arg(
+            Arg::with_name("man")
+                .short('m')
+                .long("man")
+                .help("Generate man pages as well"),
+        )
+        .arg(
+            Arg::with_name("out-dir")
+                .short('o')
+                .long("out-dir")
+                .value_name("DIR")
+                .help("The directory to write the files to [default: stdout, for a single output]"),
+        )
+        .arg(
+            Arg::with_name("manifest")
+                .long("manifest")
+                .value_name("FILE")
+                .requires("out-dir")
+                .help("Also write a JSON manifest of the written files to FILE in the output directory"),
+        )
+        .arg(
+            Arg::with_name("bin-name")
+                .short('b')
+                .long("bin-name")
+                .value_name("NAME")
+                .multiple_occurrences(true)
+                .help(
+                    "The name of the binary to complete [default: the bin_name of the spec], \
+                     repeat to also register the completions for other names of the binary",
+                ),
+        )
+        .arg(
+            Arg::with_name("include-hidden")
+                .long("include-hidden")
+                .help("Also complete and document the hidden arguments and subcommands"),
+        )
+}
+
+fn main() {
+    let matches = build_cli().get_matches();
+    if let Err(e) = run(&matches) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(matches: &ArgMatches) -> CliResult<()> {
+    let mut spec = read_spec(matches.value_of("spec").unwrap(), matches.value_of("format"))?;
+    if let Some(mut bin_names) = matches.values_of("bin-name") {
+        spec.set_bin_name(bin_names.next().unwrap());
+        spec.bin_aliases = bin_names.map(String::from).collect();
+    }
+    if matches.is_present("include-hidden") {
+        spec.unhide();
+    }
+
+    let shells: Vec<Shell> = if matches.is_present("all-shells") {
+        Shell::all().to_vec()
+    } else {
+        matches
+            .values_of("shell")
+            .map_or(vec![], |v| v.map(|s| s.parse().unwrap()).collect())
+    };
+    let fig = matches.is_present("fig");
+    let carapace = matches.is_present("carapace");
+    let man = matches.is_present("man");
+
+    match matches.value_of("out-dir") {
+        Some(out_dir) => {
+            let mut opts = GenOptions::new()
+                .shells(&shells)
+                .fig(fig)
+                .carapace(carapace)
+                .man(man);
+            if let Some(name) = matches.value_of("manifest") {
+                opts = opts.manifest_file(name);
+            }
+            for entry in gen_all_from_spec(&spec, out_dir, &opts)?.entries {
+                println!("{}", entry.path.display());
+            }
+            Ok(())
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut stdout = stdout.lock();
+            match (shells.len() + fig as usize + carapace as usize, man) {
+                (1, false) if fig => {
+                    generate_from_spec_with::<FigGen, _>(&spec, &mut stdout)?;
+                    Ok(())
+                }
+                (1, false) if carapace => {
+                    generate_from_spec_with::<CarapaceGen, _>(&spec, &mut stdout)?;
+                    Ok(())
+                }
+                (1, false) => {
+                    generate_completions_from_spec(&spec, shells[0], &mut stdout)?;
+                    Ok(())
+                }
+                (0, true) => {
+                    let mut manuals = gen_manuals_from_spec(&spec);
+                    if manuals.len() > 1 {
+                        return Err("--out-dir is required to generate more than one file".into());
+                    }
+                    stdout.write_all(manuals.remove(0).render().as_bytes())?;
+                    Ok(())
+                }
+                (0, false) => Err("nothing to generate, use --shell, --all-shells, --fig, \
+                                   --carapace or --man"
+                    .into()),
+                _ => Err("--out-dir is required to generate more than one file".into()),
+            }
+        }
+    }
+}
+
+fn read_spec(path: &str, format: Option<&str>) -> CliResult<CommandSpec> {
+    let mut contents = String::new();
+    if path == "-" {
+        io::stdin().read_to_string(&mut contents)?;
+    } else {
+        File::open(path)?.read_to_string(&mut contents)?;
+    }
+
+    let yaml = match format {
+        Some(f) => f == "yaml",
+        None => path.ends_with(".yaml") || path.ends_with(".yml"),
+    };
+    if yaml {
+        Ok(CommandSpec::from_yaml(&contents)?)
+    } else {
+        Ok(CommandSpec::from_json(&contents)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn write_spec(name: &str, contents: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("{}-{}", process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn read_spec_guesses_format() {
+        let json = write_spec("deploy.json", r#"{ "version": 1, "command": { "name": "deploy" } }"#);
+        let yaml = write_spec("deploy.yml", "version: 1\ncommand:\n  name: deploy\n");
+        let forced = write_spec("deploy.spec", "version: 1\ncommand:\n  name: deploy\n");
+
+        assert_eq!(read_spec(json.to_str().unwrap(), None).unwrap().bin_name, "deploy");
+        assert_eq!(read_spec(yaml.to_str().unwrap(), None).unwrap().bin_name, "deploy");
+        assert!(read_spec(forced.to_str().unwrap(), None).is_err());
+        assert_eq!(read_spec(forced.to_str().unwrap(), Some("yaml")).unwrap().bin_name, "deploy");
+
+        for path in &[json, yaml, forced] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn several_man_pages_need_out_dir() {
+        let spec = write_spec(
+            "rollback.json",
+            r#"{ "version": 1, "command": { "name": "deploy", "subcommands": [{ "name": "rollback" }] } }"#,
+        );
+        let matches = build_cli().get_matches_from(vec![spec.to_str().unwrap(), "--man"]);
+
+        let err = run(&matches).unwrap_err();
+        assert_eq!(err.to_string(), "--out-dir is required to generate more than one file");
+
+        fs::remove_file(spec).unwrap();
+    }
+}
@@ -0,0 +1,560 @@
+// Std
+use std::env;
+use std::fmt;
+use std::io::{self, Write};
+
+// Internal
+use clap;
+use errors::{Error, Result};
+use shells::{self, Shell};
+use spec::{ArgKind, ArgSpec, CommandSpec};
+use {prepare, INTERNAL_ERROR_MSG};
+
+/// The environment variable a [dynamic shim](./fn.generate_dynamic_shim.html) sets (to the name
+/// of the shell) when it calls back into the binary to ask for candidates
+pub const COMPLETE_ENV: &'static str = "CLAP_GENERATE_COMPLETE";
+
+/// The environment variable holding the index of the word being completed, `0` being the binary
+/// itself
+pub const CWORD_ENV: &'static str = "CLAP_GENERATE_CWORD";
+
+/// A single completion candidate, i.e. a subcommand, a switch or a value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    /// The text inserted on the command line
+    pub value: String,
+    /// The description shown next to the candidate by shells which support it
+    pub help: Option<String>,
+}
+
+impl Candidate {
+    /// Creates a candidate without a description
+    pub fn new<S: Into<String>>(value: S) -> Self {
+        Candidate {
+            value: value.into(),
+            help: None,
+        }
+    }
+
+    /// Sets the description of the candidate
+    pub fn help<S: Into<String>>(mut self, help: S) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+}
+
+type ValuesFn<'a> = Box<dyn Fn(&str) -> Vec<Candidate> + 'a>;
+
+/// Completes a partial command line at runtime, which allows completing values that are only
+/// known at runtime (i.e. branch names or configuration profiles) rather than baked into a
+/// static completion script.
+///
+/// The shell side is a small shim from [`generate_dynamic_shim`] which calls the binary itself
+/// with [`COMPLETE_ENV`] set and the words of the command line after a `--`. The binary has to
+/// hand off to [`complete_from_env`] *before* parsing its arguments as usual.
+///
+/// # Examples
+///
+/// ```ignore
+/// fn main() {
+///     let completer = DynamicCompleter::from_app(&mut cli::build_cli(), "myapp")
+///         .unwrap()
+///         .values_for("checkout", "branch", |_current| {
+///             git::branches().into_iter().map(Candidate::new).collect()
+///         });
+///     if completer.complete_from_env().unwrap() {
+///         return;
+///     }
+///
+///     let m = cli::build_cli().get_matches();
+///     // normal logic continues...
+/// }
+/// ```
+///
+/// [`generate_dynamic_shim`]: ./fn.generate_dynamic_shim.html
+/// [`COMPLETE_ENV`]: ./constant.COMPLETE_ENV.html
+/// [`complete_from_env`]: #method.complete_from_env
+pub struct DynamicCompleter<'a> {
+    spec: CommandSpec,
+    values: Vec<(String, String, ValuesFn<'a>)>,
+}
+
+impl<'a> DynamicCompleter<'a> {
+    /// Creates a completer for the command described by `spec`
+    pub fn new(spec: CommandSpec) -> Self {
+        DynamicCompleter {
+            spec: spec,
+            values: vec![],
+        }
+    }
+
+    /// Creates a completer for `app`, which is invoked as `bin_name`
+    pub fn from_app<S: Into<String>>(app: &mut clap::App, bin_name: S) -> Result<Self> {
+        Ok(DynamicCompleter::new(prepare(app, bin_name)?))
+    }
+
+    /// Completes the values of the argument `arg` of the subcommand at `path` (the space
+    /// delimited names of the subcommands below the root, i.e. `"remote add"`, or `""` for the
+    /// root itself) with the candidates returned by `f`.
+    ///
+    /// `f` is called with the (possibly empty) word being completed. The candidates don't need
+    /// to be filtered by it, which happens afterwards.
+    pub fn values_for<F>(mut self, path: &str, arg: &str, f: F) -> Self
+    where
+        F: Fn(&str) -> Vec<Candidate> + 'a,
+    {
+        self.values.push((path.into(), arg.into(), Box::new(f)));
+        self
+    }
+
+    /// Gets the candidates for the word at index `cword` of `words`, where `words[0]` is the
+    /// binary itself. `cword` may point one past the last word, in which case an empty word is
+    /// completed.
+    ///
+    /// Words which bash split on `=` or `:` (i.e. `--opt`, `=`, `value`) are joined back first.
+    pub fn complete<S: AsRef<str>>(&self, words: &[S], cword: usize) -> Vec<Candidate> {
+        let (words, cword) = join_word_breaks(words, cword);
+        let words: Vec<&str> = words.iter().map(|w| &**w).collect();
+        let current = words.get(cword).cloned().unwrap_or("");
+        debugln!("DynamicCompleter::complete: cword={}, current={}", cword, current);
+
+        let end = cword.min(words.len()).max(1);
+        let state = ParseState::parse(&self.spec, words.get(1..end).unwrap_or(&[]));
+        let candidates = if let Some(arg) = state.pending {
+            self.values_of(&state, arg, current, "")
+        } else if !state.escaped && current.starts_with("--") && current.contains('=') {
+            let (switch, value) = current.split_at(current.find('=').unwrap() + 1);
+            match state.cmd.find_long(&switch[2..switch.len() - 1]) {
+                Some(arg) => self.values_of(&state, arg, value, switch),
+                None => vec![],
+            }
+        } else if !state.escaped && current.starts_with('-') {
+            switches_of(state.cmd)
+        } else {
+            let mut candidates = vec![];
+            if state.positionals == 0 {
+                candidates.extend(
+                    state
+                        .cmd
+                        .subcommands
+                        .iter()
+                        .filter(|sc| !sc.hidden)
+                        .map(|sc| Candidate {
+                            value: sc.name.clone(),
+                            help: sc.about.clone(),
+                        }),
+                );
+            }
+            if let Some(arg) = positional_at(state.cmd, state.positionals + 1) {
+                candidates.extend(self.values_of(&state, arg, current, ""));
+            }
+            candidates
+        };
+
+        candidates
+            .into_iter()
+            .filter(|c| c.value.starts_with(current))
+            .collect()
+    }
+
+    /// If the binary was called by a [dynamic shim](./fn.generate_dynamic_shim.html), prints
+    /// the candidates for the command line passed after `--` to stdout (one per line, the
+    /// description separated by a tab) and returns `true`. Otherwise does nothing and returns
+    /// `false`, so the binary can carry on as usual.
+    pub fn complete_from_env(&self) -> Result<bool> {
+        if env::var_os(COMPLETE_ENV).is_none() {
+            return Ok(false);
+        }
+        let words: Vec<String> = env::args().skip_while(|a| a != "--").skip(1).collect();
+        let cword = match env::var(CWORD_ENV).ok().and_then(|c| c.parse().ok()) {
+            Some(cword) => cword,
+            None => words.len().saturating_sub(1),
+        };
+
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        for c in self.complete(&words, cword) {
+            match c.help {
+                Some(ref help) => writeln!(stdout, "{}\t{}", sanitize(&c.value), sanitize(help))?,
+                None => writeln!(stdout, "{}", sanitize(&c.value))?,
+            }
+        }
+        Ok(true)
+    }
+
+    // Gets the values of `arg` for the word `current`, each prefixed by `prefix` (i.e. `--opt=`)
+    fn values_of(
+        &self,
+        state: &ParseState,
+        arg: &ArgSpec,
+        current: &str,
+        prefix: &str,
+    ) -> Vec<Candidate> {
+        let custom = self
+            .values
+            .iter()
+            .find(|&&(ref path, ref name, _)| *path == state.path.join(" ") && *name == arg.name);
+        let values = match custom {
+            Some(&(_, _, ref f)) => f(current),
+            None => arg.possible_values.iter().map(Candidate::new).collect(),
+        };
+        values
+            .into_iter()
+            .map(|c| Candidate {
+                value: format!("{}{}", prefix, c.value),
+                help: c.help,
+            })
+            .collect()
+    }
+}
+
+impl<'a> fmt::Debug for DynamicCompleter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DynamicCompleter")
+            .field("spec", &self.spec)
+            .field(
+                "values",
+                &self.values.iter().map(|&(ref p, ref a, _)| (p, a)).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+// Where the command line before the word being completed left off
+struct ParseState<'s> {
+    cmd: &'s CommandSpec,
+    // The subcommands below the root, i.e. ["remote", "add"]
+    path: Vec<&'s str>,
+    // The option still waiting for its value
+    pending: Option<&'s ArgSpec>,
+    // The number of positional values of `cmd` seen so far
+    positionals: u64,
+    // Whether a `--` has been seen, after which everything is a positional value
+    escaped: bool,
+}
+
+impl<'s> ParseState<'s> {
+    fn parse(root: &'s CommandSpec, words: &[&str]) -> Self {
+        let mut state = ParseState {
+            cmd: root,
+            path: vec![],
+            pending: None,
+            positionals: 0,
+            escaped: false,
+        };
+        for &word in words {
+            if state.pending.take().is_some() {
+                continue;
+            }
+            if !state.escaped && word == "--" {
+                state.escaped = true;
+            } else if !state.escaped && word.starts_with("--") {
+                if !word.contains('=') {
+                    state.pending = state.cmd.find_long(&word[2..]).filter(|a| a.takes_value());
+                }
+            } else if !state.escaped && word.starts_with('-') && word.len() > 1 {
+                // A cluster of shorts, i.e. `-vvo` or `-ofile`, the first one which takes a
+                // value ends it
+                let shorts = &word[1..];
+                for (i, c) in shorts.char_indices() {
+                    match state.cmd.args.iter().find(|a| a.short == Some(c)) {
+                        Some(arg) if arg.takes_value() => {
+                            if i + c.len_utf8() == shorts.len() {
+                                state.pending = Some(arg);
+                            }
+                            break;
+                        }
+                        _ => (),
+                    }
+                }
+            } else if let (0, Some(sc)) = (state.positionals, state.cmd.find_subcommand(word)) {
+                state.cmd = sc;
+                state.path.push(&*sc.name);
+            } else {
+                state.positionals += 1;
+            }
+        }
+        state
+    }
+}
+
+// Joins the words bash split on the `=` and `:` of COMP_WORDBREAKS back together, i.e. `--opt`,
+// `=`, `value` or `host`, `:`, `80`, and gets the index of the word at `cword` among them
+fn join_word_breaks<S: AsRef<str>>(words: &[S], cword: usize) -> (Vec<String>, usize) {
+    let mut joined: Vec<String> = vec![];
+    let mut joined_cword = None;
+    let mut glue_next = false;
+    for (i, word) in words.iter().map(|w| w.as_ref()).enumerate() {
+        let is_break = !word.is_empty() && word.chars().all(|c| c == '=' || c == ':');
+        if joined.len() > 1 && (is_break || (glue_next && !word.is_empty())) {
+            joined.last_mut().expect(INTERNAL_ERROR_MSG).push_str(word);
+            glue_next = is_break;
+        } else {
+            joined.push(word.to_string());
+            glue_next = false;
+        }
+        if i == cword {
+            joined_cword = Some(joined.len() - 1);
+        }
+    }
+    let cword = joined_cword.unwrap_or(joined.len() + cword - words.len());
+    (joined, cword)
+}
+
+// Finds the positional arg of `cmd` which takes the value at `index` (starting at 1), which is the
+// last one for any further values if it accepts more than one
+fn positional_at(cmd: &CommandSpec, index: u64) -> Option<&ArgSpec> {
+    let mut positionals: Vec<_> = cmd.positionals().collect();
+    positionals.sort_by_key(|p| p.index);
+    match positionals.get(index as usize - 1) {
+        Some(p) => Some(*p),
+        None => positionals
+            .last()
+            .cloned()
+            .filter(|p| p.multiple_values || p.multiple_occurrences),
+    }
+}
+
+// Gets the (visible) short and long switches of `cmd`
+fn switches_of(cmd: &CommandSpec) -> Vec<Candidate> {
+    let mut candidates = vec![];
+    for arg in cmd.args.iter().filter(|a| a.kind != ArgKind::Positional && !a.hidden) {
+        if let Some(ref long) = arg.long {
+            candidates.push(Candidate {
+                value: format!("--{}", long),
+                help: arg.help.clone(),
+            });
+        }
+        if let Some(short) = arg.short {
+            candidates.push(Candidate {
+                value: format!("-{}", short),
+                help: arg.help.clone(),
+            });
+        }
+    }
+    candidates
+}
+
+// Tabs and newlines are the separators of the protocol
+fn sanitize(s: &str) -> String { s.replace('\t', " ").replace('\n', " ") }
+
+/// Writes the shim for `for_shell` which registers dynamic completions for `bin_name`, i.e. it
+/// calls back into `bin_name` (see [`DynamicCompleter`](./struct.DynamicCompleter.html)) each
+/// time the user asks for completions.
+///
+/// The shim is written to the same file as the static completions would be (see
+/// [`Generator::file_name`](./trait.Generator.html#tymethod.file_name)). The Nushell shim needs
+/// Nushell 0.94 or later.
+pub fn generate_dynamic_shim<W: Write>(
+    bin_name: &str,
+    for_shell: Shell,
+    buf: &mut W,
+) -> Result<()> {
+    let shim = match for_shell {
+        Shell::Bash => format!(
+            r#"_{name}() {{
+    local words cword line cur rest candidate
+    if declare -F _get_comp_words_by_ref >/dev/null 2>&1; then
+        _get_comp_words_by_ref -n =: words cword
+    else
+        words=("${{COMP_WORDS[@]}}")
+        cword=$COMP_CWORD
+    fi
+
+    # The candidates complete the whole word, but bash only replaces what follows the last
+    # character of COMP_WORDBREAKS in it, i.e. the value of `--opt=value`
+    line="${{COMP_LINE:0:COMP_POINT}}"
+    cur="${{line##*[[:space:]]}}"
+    rest="${{line##*["$COMP_WORDBREAKS"]}}"
+    COMPREPLY=()
+    while IFS= read -r candidate; do
+        COMPREPLY+=("${{candidate:$(( ${{#cur}} - ${{#rest}} ))}}")
+    done < <({env}=bash {cword}="${{cword}}" "${{words[0]}}" -- "${{words[@]}}" 2>/dev/null | cut -f1)
+    return 0
+}}
+
+complete -F _{name} -o bashdefault -o default {name}
+"#,
+            name = bin_name,
+            env = COMPLETE_ENV,
+            cword = CWORD_ENV
+        ),
+        Shell::Zsh => format!(
+            r#"#compdef {name}
+
+_{name}() {{
+    local -a candidates described
+    local candidate value desc
+    candidates=("${{(@f)$({env}=zsh {cword}=$((CURRENT - 1)) ${{words[1]}} -- "${{words[@]}}" 2>/dev/null)}}")
+    for candidate in "${{candidates[@]}}"; do
+        [[ -z $candidate ]] && continue
+        value=${{candidate%%$'\t'*}}
+        desc=${{candidate#*$'\t'}}
+        [[ $desc == $candidate ]] && desc=''
+        described+=("${{value//:/\\:}}:$desc")
+    done
+    if (( ${{#described}} )); then
+        _describe -t values '{name}' described
+    else
+        _files
+    fi
+}}
+
+_{name} "$@"
+"#,
+            name = bin_name,
+            env = COMPLETE_ENV,
+            cword = CWORD_ENV
+        ),
+        Shell::Fish => format!(
+            "complete -c {name} -f -a '(env {env}=fish {cword}=(count (commandline -opc)) \
+             {name} -- (commandline -opc) (commandline -ct))'\n",
+            name = bin_name,
+            env = COMPLETE_ENV,
+            cword = CWORD_ENV
+        ),
+        Shell::PowerShell => format!(
+            r#"
+Register-ArgumentCompleter -Native -CommandName '{name}' -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+
+    $words = @($commandAst.CommandElements |
+        Where-Object {{ $_.Extent.StartOffset -lt $cursorPosition }} |
+        ForEach-Object {{ $_.ToString() }})
+    $cword = $words.Count
+    if ($wordToComplete -ne '') {{
+        $cword -= 1
+    }}
+    $env:{env} = 'powershell'
+    $env:{cword} = $cword
+    $candidates = & $words[0] -- @words 2>$null
+    Remove-Item Env:\{env}, Env:\{cword}
+
+    $candidates | ForEach-Object {{
+        $value, $desc = $_ -split "`t", 2
+        if (-not $desc) {{
+            $desc = $value
+        }}
+        [System.Management.Automation.CompletionResult]::new($value, $value, 'ParameterValue', $desc)
+    }}
+}}
+"#,
+            name = bin_name,
+            env = COMPLETE_ENV,
+            cword = CWORD_ENV
+        ),
+        Shell::Elvish => format!(
+            r#"
+edit:completion:arg-completer[{name}] = [@words]{{
+    E:{env}=elvish E:{cword}=(- (count $words) 1) $words[0] -- $@words | each [line]{{
+        parts = [(splits "\t" $line)]
+        if (> (count $parts) 1) {{
+            edit:complex-candidate $parts[0] &display-suffix=' '$parts[1]
+        }} else {{
+            put $parts[0]
+        }}
+    }}
+}}
+"#,
+            name = bin_name,
+            env = COMPLETE_ENV,
+            cword = CWORD_ENV
+        ),
+        Shell::Nushell => format!(
+            r#"module completions {{
+  def "nu-complete {name}" [context: string] {{
+    let words = ($context | split row -r '\s+')
+    let cword = (($words | length) - 1 | into string)
+    with-env {{ {env}: nushell, {cword_env}: $cword }} {{
+      ^{name} -- ...$words
+    }} | lines | each {{|line|
+      let parts = ($line | split row "	")
+      {{ value: $parts.0, description: $parts.1? }}
+    }}
+  }}
+
+  export extern "{name}" [
+    ...args: string@"nu-complete {name}"
+  ]
+}}
+
+export use completions *
+"#,
+            name = bin_name,
+            env = COMPLETE_ENV,
+            cword_env = CWORD_ENV
+        ),
+        Shell::Tcsh => format!(
+            r#"complete {name} 'p,*,`sh -c '\''set -- $COMMAND_LINE; n=$#; case "$COMMAND_LINE" in *" ") n=$((n + 1)); set -- "$@" "";; esac; {env}=tcsh {cword}=$((n - 1)) "$1" -- "$@" 2>/dev/null | cut -f1'\''`,'
+"#,
+            name = bin_name,
+            env = COMPLETE_ENV,
+            cword = CWORD_ENV
+        ),
+        Shell::Xonsh => format!(
+            r#"import subprocess
+
+from xonsh.completers.tools import RichCompletion
+
+
+def _{ident}_dynamic_completer(prefix, line, begidx, endidx, ctx):
+    words = line[:begidx].split() + [prefix]
+    if words[0] != '{name}':
+        return None
+    env = __xonsh__.env.detype()
+    env['{env}'] = 'xonsh'
+    env['{cword}'] = str(len(words) - 1)
+    try:
+        out = subprocess.run(
+            [words[0], '--'] + words, env=env, stdout=subprocess.PIPE, universal_newlines=True
+        ).stdout
+    except OSError:
+        return None
+    completions = set()
+    for candidate in out.splitlines():
+        value, _, desc = candidate.partition('\t')
+        if value:
+            completions.add(RichCompletion(value, description=desc))
+    return completions or None
+
+
+completer add {name} _{ident}_dynamic_completer "start"
+"#,
+            name = bin_name,
+            ident = shells::identifier(bin_name),
+            env = COMPLETE_ENV,
+            cword = CWORD_ENV
+        ),
+        Shell::Clink => format!(
+            r#"local function {ident}_complete(word, word_index, line_state)
+    local count = line_state:getwordcount()
+    local words = {{}}
+    for i = 1, count do
+        table.insert(words, '"' .. line_state:getword(i) .. '"')
+    end
+    local command = 'set {env}=clink&& set {cword}=' .. (count - 1) ..
+        '&& {name} -- ' .. table.concat(words, ' ') .. ' 2>nul'
+    local matches = {{}}
+    local pipe = io.popen(command)
+    if pipe then
+        for line in pipe:lines() do
+            local value, desc = line:match('^([^\t]*)\t?(.*)$')
+            table.insert(matches, {{ match = value, description = desc }})
+        end
+        pipe:close()
+    end
+    return matches
+end
+
+clink.argmatcher("{name}"):addarg({{ {ident}_complete }}):loop()
+"#,
+            name = bin_name,
+            ident = shells::identifier(bin_name),
+            env = COMPLETE_ENV,
+            cword = CWORD_ENV
+        ),
+        _ => return Err(Error::UnsupportedShell),
+    };
+    w!(buf, shim.as_bytes());
+    Ok(())
+}
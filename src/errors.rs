@@ -0,0 +1,58 @@
+// Std
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::result::Result as StdResult;
+
+/// Short hand for the `Result` type returned by the fallible functions of this crate
+pub type Result<T> = StdResult<T, Error>;
+
+/// The errors which can occur while generating completion scripts or man pages
+#[derive(Debug)]
+pub enum Error {
+    /// Creating or writing to the output failed
+    Io(io::Error),
+    /// The requested [`Shell`](./enum.Shell.html) isn't supported by this version of
+    /// `clap_generate`
+    UnsupportedShell,
+    /// The `clap::App` is missing something the generators rely on (such as a `bin_name`), or
+    /// refers to something which doesn't exist (such as an unknown argument in `conflicts_with`)
+    MalformedApp(String),
+    /// A serialized [`CommandSpec`](./struct.CommandSpec.html) couldn't be read, i.e. because it
+    /// isn't valid JSON or was written for an unsupported schema version
+    InvalidSpec(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "failed to write completions: {}", e),
+            Error::UnsupportedShell => {
+                write!(f, "unsupported shell type for completion generation")
+            }
+            Error::MalformedApp(ref msg) => write!(f, "malformed clap::App: {}", msg),
+            Error::InvalidSpec(ref msg) => write!(f, "invalid command spec: {}", msg),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Io(ref e) => e.description(),
+            Error::UnsupportedShell => "unsupported shell type for completion generation",
+            Error::MalformedApp(ref msg) | Error::InvalidSpec(ref msg) => msg,
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn StdError> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self { Error::Io(e) }
+}
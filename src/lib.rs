@@ -30,6 +30,12 @@
 
 extern crate clap as _clap;
 extern crate man;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+#[cfg(feature = "yaml")]
+extern crate serde_yaml;
 
 // Re-Export of clap
 mod clap {
@@ -41,18 +47,30 @@ const INTERNAL_ERROR_MSG: &'static str = "Fatal internal error. Please consider
 
 #[macro_use]
 mod macros;
+mod dynamic;
+mod errors;
+mod manifest;
 mod manual;
 mod shells;
+mod spec;
 
+use errors::Result;
 use shells::ComplGen;
-pub use shells::Shell;
+pub use dynamic::{generate_dynamic_shim, Candidate, DynamicCompleter, COMPLETE_ENV, CWORD_ENV};
+pub use errors::Error;
+pub use manifest::{gen_all, gen_all_from_spec, GenOptions, Manifest, ManifestEntry, Output};
+pub use shells::{BashGen, CarapaceGen, ClinkGen, ElvishGen, FigGen, FishGen, Generator,
+                 NushellGen, PowerShellGen, Shell, TcshGen, XonshGen, ZshGen};
+pub use spec::{Alias, ArgKind, ArgSpec, CommandSpec, ValueHint, SPEC_VERSION};
 
+use std::borrow::Cow;
 use std::ffi::OsString;
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
 
-pub use manual::gen_manuals;
+pub use manual::{gen_manuals, gen_manuals_from_spec, gen_manuals_from_spec_with, gen_manuals_with,
+                 ManPage, ManualOptions};
 
 /// Generate a completions file for a specified shell at compile time.
 ///
@@ -137,30 +155,51 @@ pub use manual::gen_manuals;
 /// `<project>/target/debug/build/myapp-<hash>/out/myapp.bash`.
 ///
 /// Fish shell completions will use the file format `{bin_name}.fish`
+///
+/// To choose a different file name, or to register the completions for several names of the
+/// same binary, use [`gen_all`](./fn.gen_all.html) with the respective
+/// [`GenOptions`](./struct.GenOptions.html) instead.
+///
+/// # Panics
+///
+/// If the file can't be created or written to, or the `App` is malformed. See
+/// [`try_gen_completions`](./fn.try_gen_completions.html) for a version which returns an error
+/// instead.
 pub fn gen_completions<T: Into<OsString>, S: Into<String>>(
     app: &mut clap::App,
     bin_name: S,
     for_shell: Shell,
     out_dir: T,
 ) {
-    use std::error::Error;
+    if let Err(e) = try_gen_completions(app, bin_name, for_shell, out_dir) {
+        panic!("couldn't generate completion file: {}", e);
+    }
+}
 
-    let out_dir = PathBuf::from(out_dir.into());
-    let name = &*app.bin_name.as_ref().unwrap().clone();
-    let file_name = match for_shell {
-        Shell::Bash => format!("{}.bash", name),
-        Shell::Fish => format!("{}.fish", name),
-        Shell::Zsh => format!("_{}", name),
-        Shell::PowerShell => format!("_{}.ps1", name),
-        Shell::Elvish => format!("{}.elv", name),
-        _ => panic!("Unsupported shell type for completion generation"),
-    };
-
-    let mut file = match File::create(out_dir.join(file_name)) {
-        Err(why) => panic!("couldn't create completion file: {}", why.description()),
-        Ok(file) => file,
-    };
-    generate_completions_to(app, bin_name.into(), for_shell, &mut file)
+/// The fallible version of [`gen_completions`](./fn.gen_completions.html), which is better
+/// suited to contexts where a panic is unacceptable (e.g. a daemon serving completions).
+///
+/// Returns the path of the file which was written.
+///
+/// # Examples
+///
+/// ```ignore
+/// fn main() {
+///     let outdir = env::var_os("OUT_DIR").unwrap();
+///     match clap_generate::try_gen_completions(&mut build_cli(), "myapp", Shell::Bash, outdir) {
+///         Ok(path) => println!("wrote {}", path.display()),
+///         Err(e) => println!("cargo:warning=couldn't generate completions: {}", e),
+///     }
+/// }
+/// ```
+pub fn try_gen_completions<T: Into<OsString>, S: Into<String>>(
+    app: &mut clap::App,
+    bin_name: S,
+    for_shell: Shell,
+    out_dir: T,
+) -> Result<PathBuf> {
+    let spec = prepare(app, bin_name)?;
+    gen_completions_from_spec(&spec, for_shell, out_dir)
 }
 
 /// Generate a completions file for a specified shell at runtime.  Until `cargo install` can
@@ -195,17 +234,152 @@ pub fn gen_completions<T: Into<OsString>, S: Into<String>>(
 /// ```shell
 /// $ myapp generate-bash-completions > /usr/share/bash-completion/completions/myapp.bash
 /// ```
+///
+/// # Panics
+///
+/// If writing to `buf` fails, or the `App` is malformed. See
+/// [`try_generate_completions_to`](./fn.try_generate_completions_to.html) for a version which
+/// returns an error instead.
 pub fn generate_completions_to<W: Write, S: Into<String>>(
     app: &mut clap::App,
     bin_name: S,
     for_shell: Shell,
     buf: &mut W,
 ) {
+    if let Err(e) = try_generate_completions_to(app, bin_name, for_shell, buf) {
+        panic!("couldn't generate completions: {}", e);
+    }
+}
+
+/// The fallible version of [`generate_completions_to`](./fn.generate_completions_to.html).
+pub fn try_generate_completions_to<W: Write, S: Into<String>>(
+    app: &mut clap::App,
+    bin_name: S,
+    for_shell: Shell,
+    buf: &mut W,
+) -> Result<()> {
+    let spec = prepare(app, bin_name)?;
+    generate_completions_from_spec(&spec, for_shell, buf)
+}
+
+/// Generate completions (or any other output) for `app` with a custom [`Generator`] at runtime.
+///
+/// This is the extension point for completion targets that `clap_generate` doesn't ship
+/// itself; the built-in [`Shell`]s go through the same trait (e.g. `BashGen`).
+///
+/// # Examples
+///
+/// ```ignore
+/// use std::io;
+/// use clap_generate::{generate_with, BashGen};
+///
+/// generate_with::<BashGen, _, _>(&mut cli::build_cli(), "myapp", &mut io::stdout())
+///     .expect("couldn't write completions");
+/// ```
+///
+/// [`Generator`]: ./trait.Generator.html
+/// [`Shell`]: ./enum.Shell.html
+pub fn generate_with<G: Generator, W: Write, S: Into<String>>(
+    app: &mut clap::App,
+    bin_name: S,
+    buf: &mut W,
+) -> Result<()> {
+    let spec = prepare(app, bin_name)?;
+    G::generate(&visible(&spec), buf)
+}
+
+/// Builds the [`CommandSpec`] of `app` as it would be handed to the generators, i.e. to export it
+/// with [`CommandSpec::to_json`] for tools which aren't written in Rust.
+///
+/// # Examples
+///
+/// ```ignore
+/// let spec = clap_generate::command_spec(&mut cli::build_cli(), "myapp")?;
+/// fs::write("myapp.json", spec.to_json())?;
+/// ```
+///
+/// [`CommandSpec`]: ./struct.CommandSpec.html
+/// [`CommandSpec::to_json`]: ./struct.CommandSpec.html#method.to_json
+pub fn command_spec<S: Into<String>>(app: &mut clap::App, bin_name: S) -> Result<CommandSpec> {
+    prepare(app, bin_name)
+}
+
+/// Like [`try_gen_completions`](./fn.try_gen_completions.html), but generates the completions
+/// file from a [`CommandSpec`](./struct.CommandSpec.html) instead of a `clap::App`.
+///
+/// Returns the path of the file which was written.
+pub fn gen_completions_from_spec<T: Into<OsString>>(
+    spec: &CommandSpec,
+    for_shell: Shell,
+    out_dir: T,
+) -> Result<PathBuf> {
+    let out_dir = PathBuf::from(out_dir.into());
+    let path = out_dir.join(ComplGen::file_name(for_shell, &*spec.bin_name)?);
+
+    let mut file = File::create(&path)?;
+    generate_completions_from_spec(spec, for_shell, &mut file)?;
+    Ok(path)
+}
+
+/// Generate completions for a specified shell from a [`CommandSpec`] instead of a `clap::App`,
+/// i.e. one loaded with [`CommandSpec::from_json`] on behalf of a tool which isn't written in
+/// Rust.
+///
+/// Like every generator, this leaves out the hidden arguments and subcommands. Call
+/// [`CommandSpec::unhide`] on the spec first to complete them too.
+///
+/// # Examples
+///
+/// ```ignore
+/// let spec = CommandSpec::from_json(&fs::read_to_string("deploy.json")?)?;
+/// clap_generate::generate_completions_from_spec(&spec, Shell::Bash, &mut io::stdout())?;
+/// ```
+///
+/// [`CommandSpec`]: ./struct.CommandSpec.html
+/// [`CommandSpec::from_json`]: ./struct.CommandSpec.html#method.from_json
+/// [`CommandSpec::unhide`]: ./struct.CommandSpec.html#method.unhide
+pub fn generate_completions_from_spec<W: Write>(
+    spec: &CommandSpec,
+    for_shell: Shell,
+    buf: &mut W,
+) -> Result<()> {
+    spec.validate()?;
+    ComplGen::new(&visible(spec)).generate(for_shell, buf)
+}
+
+/// Like [`generate_with`](./fn.generate_with.html), but generates the output from a
+/// [`CommandSpec`](./struct.CommandSpec.html) instead of a `clap::App`.
+pub fn generate_from_spec_with<G: Generator, W: Write>(
+    spec: &CommandSpec,
+    buf: &mut W,
+) -> Result<()> {
+    spec.validate()?;
+    G::generate(&visible(spec), buf)
+}
+
+// Sets the bin_name and makes sure all args and bin_names have been propagated to every
+// subcommand, then builds the (validated) description handed off to a generator
+fn prepare<S: Into<String>>(app: &mut clap::App, bin_name: S) -> Result<CommandSpec> {
     app.bin_name = Some(bin_name.into());
     if !app.is_set(clap::AppSettings::Propagated) {
         app._build(clap::Propagation::Full);
         app._build_bin_names();
     }
+    let spec = CommandSpec::from_app(app);
+    spec.validate()?;
+    Ok(spec)
+}
 
-    ComplGen::new(app).generate(for_shell, buf)
+// Gets `spec` without its hidden arguments and subcommands, only cloning it if there are any
+fn visible<'a>(spec: &'a CommandSpec) -> Cow<'a, CommandSpec> {
+    let has_hidden = spec.all_commands().iter().any(|cmd| {
+        cmd.args.iter().any(|a| a.hidden) || cmd.subcommands.iter().any(|sc| sc.hidden)
+    });
+    if has_hidden {
+        let mut spec = spec.clone();
+        spec.remove_hidden();
+        Cow::Owned(spec)
+    } else {
+        Cow::Borrowed(spec)
+    }
 }
@@ -0,0 +1,317 @@
+// Std
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Third Party
+use clap;
+use serde::{Serialize, Serializer};
+use serde_json;
+
+// Internal
+use errors::Result;
+use manual::{gen_manuals_from_spec_with, ManualOptions};
+use shells::{CarapaceGen, ComplGen, FigGen, Generator, Shell};
+use spec::CommandSpec;
+use {generate_completions_from_spec, generate_from_spec_with, prepare, INTERNAL_ERROR_MSG};
+
+/// Writes the completions for every shell plus all man pages from
+/// [`gen_manuals`](./fn.gen_manuals.html) to `out_dir` (which is created if it doesn't exist
+/// yet), as well as the Fig and carapace specs if enabled in `options`, and returns a
+/// [`Manifest`](./struct.Manifest.html) of the written files.
+///
+/// # Examples
+///
+/// ```ignore
+/// // build.rs
+/// fn main() {
+///     let outdir = env::var_os("OUT_DIR").unwrap();
+///     let opts = GenOptions::new().manifest_file("manifest.json");
+///     let manifest = clap_generate::gen_all(&mut build_cli(), "myapp", outdir, &opts).unwrap();
+///     for entry in &manifest.entries {
+///         println!("wrote {}", entry.path.display());
+///     }
+/// }
+/// ```
+pub fn gen_all<T: Into<OsString>, S: Into<String>>(
+    app: &mut clap::App,
+    bin_name: S,
+    out_dir: T,
+    options: &GenOptions,
+) -> Result<Manifest> {
+    let spec = prepare(app, bin_name)?;
+    gen_all_from_spec(&spec, out_dir, options)
+}
+
+/// Like [`gen_all`](./fn.gen_all.html), but generates the files from a
+/// [`CommandSpec`](./struct.CommandSpec.html) instead of a `clap::App`.
+pub fn gen_all_from_spec<T: Into<OsString>>(
+    spec: &CommandSpec,
+    out_dir: T,
+    options: &GenOptions,
+) -> Result<Manifest> {
+    let out_dir = PathBuf::from(out_dir.into());
+    fs::create_dir_all(&out_dir)?;
+    let mut manifest = Manifest::default();
+
+    let spec = if options.bin_aliases.is_empty() && !options.include_hidden {
+        Cow::Borrowed(spec)
+    } else {
+        let mut spec = spec.clone();
+        spec.bin_aliases.extend(options.bin_aliases.iter().cloned());
+        if options.include_hidden {
+            spec.unhide();
+        }
+        Cow::Owned(spec)
+    };
+    let spec = &*spec;
+
+    for &shell in &options.shells {
+        let file_name = match options.file_names.iter().find(|&&(s, _)| s == shell) {
+            Some(&(_, ref name)) => name.clone(),
+            None => ComplGen::file_name(shell, &*spec.bin_name)?,
+        };
+        let path = out_dir.join(file_name);
+        let mut file = File::create(&path)?;
+        generate_completions_from_spec(spec, shell, &mut file)?;
+        manifest.push(Output::Completions(shell), path, &*spec.bin_name);
+    }
+
+    if options.fig {
+        let path = out_dir.join(FigGen::file_name(&*spec.bin_name));
+        generate_from_spec_with::<FigGen, _>(spec, &mut File::create(&path)?)?;
+        manifest.push(Output::Fig, path, &*spec.bin_name);
+    }
+
+    if options.carapace {
+        let path = out_dir.join(CarapaceGen::file_name(&*spec.bin_name));
+        generate_from_spec_with::<CarapaceGen, _>(spec, &mut File::create(&path)?)?;
+        manifest.push(Output::Carapace, path, &*spec.bin_name);
+    }
+
+    if options.man {
+        // One man page per command, named after the command it documents (i.e. `myapp.1`)
+        for page in gen_manuals_from_spec_with(spec, &options.manual_options) {
+            let path = out_dir.join(page.file_name());
+            File::create(&path)?.write_all(page.content.as_bytes())?;
+            manifest.push(Output::ManPage, path, &page.file_name());
+        }
+    }
+
+    if let Some(ref name) = options.manifest_file {
+        manifest.write_json(out_dir.join(name))?;
+    }
+    Ok(manifest)
+}
+
+/// Configures which files [`gen_all`](./fn.gen_all.html) writes
+///
+/// By default completions for all of [`Shell::all`](./enum.Shell.html#method.all) and all man
+/// pages are written, but neither the Fig and carapace specs nor a manifest file.
+#[derive(Debug, Clone)]
+pub struct GenOptions {
+    shells: Vec<Shell>,
+    man: bool,
+    fig: bool,
+    carapace: bool,
+    manifest_file: Option<String>,
+    file_names: Vec<(Shell, String)>,
+    bin_aliases: Vec<String>,
+    manual_options: ManualOptions,
+    include_hidden: bool,
+}
+
+impl Default for GenOptions {
+    fn default() -> Self {
+        GenOptions {
+            shells: Shell::all().to_vec(),
+            man: true,
+            fig: false,
+            carapace: false,
+            manifest_file: None,
+            file_names: vec![],
+            bin_aliases: vec![],
+            manual_options: ManualOptions::default(),
+            include_hidden: false,
+        }
+    }
+}
+
+impl GenOptions {
+    /// Creates the default options
+    pub fn new() -> Self { GenOptions::default() }
+
+    /// Only writes completions for `shells`
+    pub fn shells(mut self, shells: &[Shell]) -> Self {
+        self.shells = shells.to_vec();
+        self
+    }
+
+    /// Whether to write the man pages from [`gen_manuals`](./fn.gen_manuals.html) (the default)
+    pub fn man(mut self, yes: bool) -> Self {
+        self.man = yes;
+        self
+    }
+
+    /// Whether to write the spec from [`FigGen`](./struct.FigGen.html), i.e. `myapp.ts`
+    pub fn fig(mut self, yes: bool) -> Self {
+        self.fig = yes;
+        self
+    }
+
+    /// Whether to write the spec from [`CarapaceGen`](./struct.CarapaceGen.html), i.e.
+    /// `myapp.yaml`
+    pub fn carapace(mut self, yes: bool) -> Self {
+        self.carapace = yes;
+        self
+    }
+
+    /// Renders the man pages with `options` (see
+    /// [`gen_manuals_with`](./fn.gen_manuals_with.html))
+    pub fn manual_options(mut self, options: ManualOptions) -> Self {
+        self.manual_options = options;
+        self
+    }
+
+    /// Also completes and documents the hidden arguments and subcommands, which are left out by
+    /// default
+    pub fn include_hidden(mut self, yes: bool) -> Self {
+        self.include_hidden = yes;
+        self
+    }
+
+    /// Writes the completions for `shell` to a file called `name` instead of the conventional
+    /// one (i.e. `myapp` instead of `myapp.bash`)
+    pub fn file_name<S: Into<String>>(mut self, shell: Shell, name: S) -> Self {
+        self.file_names.retain(|&(s, _)| s != shell);
+        self.file_names.push((shell, name.into()));
+        self
+    }
+
+    /// Also registers the completions for `name`, i.e. a symlink to a multi-call binary. May be
+    /// called more than once.
+    ///
+    /// The files are still named after the `bin_name` passed to [`gen_all`](./fn.gen_all.html).
+    pub fn bin_alias<S: Into<String>>(mut self, name: S) -> Self {
+        self.bin_aliases.push(name.into());
+        self
+    }
+
+    /// Also writes the [`Manifest`](./struct.Manifest.html) as JSON to a file called `name` in
+    /// the output directory
+    pub fn manifest_file<S: Into<String>>(mut self, name: S) -> Self {
+        self.manifest_file = Some(name.into());
+        self
+    }
+}
+
+/// What kind of file a [`ManifestEntry`](./struct.ManifestEntry.html) describes
+#[derive(Debug, Copy, Clone)]
+pub enum Output {
+    /// A completion script for the given shell
+    Completions(Shell),
+    /// A completion spec for Fig (Amazon Q) and inshellisense
+    Fig,
+    /// A spec for carapace
+    Carapace,
+    /// A man page
+    ManPage,
+}
+
+impl Output {
+    /// The name used for the output in the JSON manifest, i.e. `bash` or `man`
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Output::Completions(Shell::Bash) => "bash",
+            Output::Completions(Shell::Fish) => "fish",
+            Output::Completions(Shell::Zsh) => "zsh",
+            Output::Completions(Shell::PowerShell) => "powershell",
+            Output::Completions(Shell::Elvish) => "elvish",
+            Output::Completions(Shell::Nushell) => "nushell",
+            Output::Completions(Shell::Tcsh) => "tcsh",
+            Output::Completions(Shell::Xonsh) => "xonsh",
+            Output::Completions(Shell::Clink) => "clink",
+            Output::Completions(_) => "unsupported",
+            Output::Fig => "fig",
+            Output::Carapace => "carapace",
+            Output::ManPage => "man",
+        }
+    }
+}
+
+impl Serialize for Output {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/// A file written by [`gen_all`](./fn.gen_all.html)
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestEntry {
+    /// What the file contains
+    #[serde(rename = "format")]
+    pub output: Output,
+    /// Where the file was written to
+    pub path: PathBuf,
+    /// Where packages conventionally install the file, relative to the installation prefix
+    /// (i.e. `/usr` or `/usr/local`), if there is such a convention
+    pub install_path: Option<PathBuf>,
+}
+
+/// The list of files written by [`gen_all`](./fn.gen_all.html), i.e. for handing off to a
+/// packager
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Manifest {
+    /// One entry per written file, in the order they were written
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Serializes the manifest to pretty printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect(INTERNAL_ERROR_MSG)
+    }
+
+    /// Writes the manifest as JSON to `path`
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(self.to_json().as_bytes())?;
+        Ok(())
+    }
+
+    fn push(&mut self, output: Output, path: PathBuf, bin_name: &str) {
+        self.entries.push(ManifestEntry {
+            output: output,
+            install_path: install_path(output, bin_name),
+            path: path,
+        });
+    }
+}
+
+// Gets the location packages conventionally install `output` for the binary `bin_name` to, or
+// the man page called `bin_name` (i.e. `myapp-remote.1`)
+fn install_path(output: Output, bin_name: &str) -> Option<PathBuf> {
+    let path = match output {
+        Output::Completions(Shell::Bash) => {
+            format!("share/bash-completion/completions/{}", bin_name)
+        }
+        Output::Completions(Shell::Fish) => {
+            format!("share/fish/vendor_completions.d/{}.fish", bin_name)
+        }
+        Output::Completions(Shell::Zsh) => format!("share/zsh/site-functions/_{}", bin_name),
+        Output::Completions(Shell::Elvish) => format!("share/elvish/lib/{}.elv", bin_name),
+        Output::Completions(Shell::Nushell) => {
+            format!("share/nushell/vendor/autoload/{}.nu", bin_name)
+        }
+        Output::ManPage => {
+            let section = bin_name.rsplit('.').next().expect(INTERNAL_ERROR_MSG);
+            format!("share/man/man{}/{}", section, bin_name)
+        }
+        // Fig specs are compiled into the `withfig/autocomplete` repository, and carapace only
+        // reads specs from the user's config directory
+        Output::Completions(_) | Output::Fig | Output::Carapace => return None,
+    };
+    Some(PathBuf::from(path))
+}
@@ -1,38 +1,274 @@
 use clap;
 use man;
 
-/// Generate a man page from a `clap::App` instance.
-// NOTE(yw): in future versions we should allow this to recurse and generate
-// man pages for subcommands. We're returning a vector now to support this in
-// future versions.
+use spec::{ArgKind, ArgSpec, CommandSpec};
+
+/// Generate the man pages from a `clap::App` instance, one for the `App` itself followed by one
+/// for each of its subcommands at any depth (i.e. `myapp-remote-add(1)`).
 pub fn gen_manuals(app: &clap::App) -> Vec<man::Manual> {
-    let mut manual = man::Manual::new(&app.name);
+    gen_manuals_from_spec(&CommandSpec::from_app(app))
+}
+
+/// Generate the man pages from a [`CommandSpec`](./struct.CommandSpec.html), i.e. one loaded
+/// from JSON with [`CommandSpec::from_json`](./struct.CommandSpec.html#method.from_json).
+///
+/// The pages are in the same order as
+/// [`CommandSpec::all_commands`](./struct.CommandSpec.html#method.all_commands).
+pub fn gen_manuals_from_spec(cmd: &CommandSpec) -> Vec<man::Manual> {
+    let mut manuals = vec![];
+    gen_manuals_of(&::visible(cmd), None, &[], "1", &mut manuals);
+    manuals
+}
+
+/// Like [`gen_manuals`](./fn.gen_manuals.html), but renders the pages with the `.TH` header and
+/// extra sections configured by `options`.
+///
+/// # Examples
+///
+/// ```ignore
+/// let options = ManualOptions::new()
+///     .date("2018-06-01")
+///     .example("Clone a repository", "myapp clone https://example.com/repo.git")
+///     .section("exit status", &["0 on success, 1 on any error."]);
+/// for page in gen_manuals_with(&build_cli(), &options) {
+///     fs::write(page.file_name(), page.content)?;
+/// }
+/// ```
+pub fn gen_manuals_with(app: &clap::App, options: &ManualOptions) -> Vec<ManPage> {
+    gen_manuals_from_spec_with(&CommandSpec::from_app(app), options)
+}
+
+/// Like [`gen_manuals_with`](./fn.gen_manuals_with.html), but generates the pages from a
+/// [`CommandSpec`](./struct.CommandSpec.html) instead of a `clap::App`.
+pub fn gen_manuals_from_spec_with(cmd: &CommandSpec, options: &ManualOptions) -> Vec<ManPage> {
+    let source = match options.source {
+        Some(ref source) => source.clone(),
+        None => match cmd.version {
+            Some(ref version) => format!("{} {}", cmd.bin_name, version),
+            None => cmd.bin_name.clone(),
+        },
+    };
+
+    let cmd = ::visible(cmd);
+    let mut pages = vec![];
+    let mut manuals = vec![];
+    gen_manuals_of(&cmd, None, &[], &options.man_section, &mut manuals);
+    for (i, (mut manual, c)) in manuals
+        .into_iter()
+        .zip(cmd.all_commands())
+        .enumerate()
+    {
+        // The extra content describes the whole program, so it only goes on the main page
+        if i == 0 {
+            for &(ref text, ref command) in &options.examples {
+                manual = manual.example(man::Example::new().text(text).command(command));
+            }
+            for &(ref name, ref paragraphs) in &options.sections {
+                let mut section = man::Section::new(name);
+                for paragraph in paragraphs {
+                    section = section.paragraph(paragraph);
+                }
+                manual = manual.custom(section);
+            }
+        }
+
+        let name = page_name(c);
+        let header = format!(
+            ".TH {} {} {} {} {}",
+            th_field(&name.to_uppercase()),
+            th_field(&options.man_section),
+            th_field(options.date.as_ref().map_or("", |s| &**s)),
+            th_field(&source),
+            th_field(&options.manual),
+        );
+        let content = manual
+            .render()
+            .lines()
+            .map(|line| if line.starts_with(".TH ") { &*header } else { line })
+            .collect::<Vec<_>>()
+            .join("\n");
+        pages.push(ManPage {
+            name: name,
+            section: options.man_section.clone(),
+            content: content + "\n",
+        });
+    }
+    pages
+}
+
+// Quotes a field of the `.TH` header
+fn th_field(s: &str) -> String { format!("\"{}\"", s.replace("\"", "\\(dq")) }
+
+/// Configures the pages rendered by [`gen_manuals_with`](./fn.gen_manuals_with.html)
+///
+/// By default the pages are in section 1 of the "User Commands" manual, have no date, and their
+/// source is the name and version of the program.
+#[derive(Debug, Clone)]
+pub struct ManualOptions {
+    man_section: String,
+    date: Option<String>,
+    source: Option<String>,
+    manual: String,
+    examples: Vec<(String, String)>,
+    sections: Vec<(String, Vec<String>)>,
+}
+
+impl Default for ManualOptions {
+    fn default() -> Self {
+        ManualOptions {
+            man_section: String::from("1"),
+            date: None,
+            source: None,
+            manual: String::from("User Commands"),
+            examples: vec![],
+            sections: vec![],
+        }
+    }
+}
+
+impl ManualOptions {
+    /// Creates the default options
+    pub fn new() -> Self { ManualOptions::default() }
+
+    /// The section of the manual the pages belong to, i.e. `8` for system administration
+    /// commands
+    pub fn man_section<S: Into<String>>(mut self, section: S) -> Self {
+        self.man_section = section.into();
+        self
+    }
+
+    /// The date of the last change to the pages, i.e. `2018-06-01`
+    pub fn date<S: Into<String>>(mut self, date: S) -> Self {
+        self.date = Some(date.into());
+        self
+    }
+
+    /// Where the program comes from, i.e. `myapp 1.0` (the default) or `GNU coreutils`
+    pub fn source<S: Into<String>>(mut self, source: S) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// The title of the manual, i.e. `User Commands` (the default)
+    pub fn manual<S: Into<String>>(mut self, title: S) -> Self {
+        self.manual = title.into();
+        self
+    }
+
+    /// Adds an example invocation of `command`, described by `text`, to the EXAMPLES section of
+    /// the main page. May be called more than once.
+    pub fn example<S: Into<String>, T: Into<String>>(mut self, text: S, command: T) -> Self {
+        self.examples.push((text.into(), command.into()));
+        self
+    }
+
+    /// Adds a section called `name` (i.e. `exit status`, `files` or `bugs`) with one or more
+    /// paragraphs to the main page. May be called more than once.
+    pub fn section<S: Into<String>>(mut self, name: S, paragraphs: &[&str]) -> Self {
+        self.sections.push((
+            name.into(),
+            paragraphs.iter().map(|p| p.to_string()).collect(),
+        ));
+        self
+    }
+}
+
+/// A rendered man page from [`gen_manuals_with`](./fn.gen_manuals_with.html)
+#[derive(Debug, Clone)]
+pub struct ManPage {
+    /// The name of the page, i.e. `myapp-remote-add`
+    pub name: String,
+    /// The section of the manual the page belongs to, i.e. `1`
+    pub section: String,
+    /// The page as roff
+    pub content: String,
+}
+
+impl ManPage {
+    /// The name of the file the page is conventionally written to, i.e. `myapp-remote-add.1`
+    pub fn file_name(&self) -> String { format!("{}.{}", self.name, self.section) }
+}
+
+// The name of the man page documenting `cmd`, i.e. `myapp-remote-add`
+fn page_name(cmd: &CommandSpec) -> String { cmd.bin_name.replace(" ", "-") }
+
+fn gen_manuals_of(
+    cmd: &CommandSpec,
+    parent: Option<&CommandSpec>,
+    globals: &[&ArgSpec],
+    section: &str,
+    manuals: &mut Vec<man::Manual>,
+) {
+    // Global args are usually propagated to the subcommands already, but not necessarily in
+    // specs loaded from JSON
+    let mut args: Vec<&ArgSpec> = cmd.args.iter().collect();
+    for global in globals {
+        if !args.iter().any(|a| a.name == global.name) {
+            args.push(global);
+        }
+    }
+    manuals.push(manual_of(cmd, parent, &args, section));
 
-    for about in &app.about {
+    let globals: Vec<&ArgSpec> = args.iter().cloned().filter(|a| a.global).collect();
+    for sc in &cmd.subcommands {
+        gen_manuals_of(sc, Some(cmd), &globals, section, manuals);
+    }
+}
+
+// The pages of the other commands are referred to in the same `section` (i.e. `myapp-test(1)`)
+fn manual_of(
+    cmd: &CommandSpec,
+    parent: Option<&CommandSpec>,
+    args: &[&ArgSpec],
+    section: &str,
+) -> man::Manual {
+    let mut manual = man::Manual::new(&page_name(cmd));
+
+    for about in &cmd.about {
         manual = manual.about(about.to_string());
     }
 
+    let description: Vec<&str> = cmd.before_help
+        .iter()
+        .chain(&cmd.long_about)
+        .map(|s| &**s)
+        .collect();
+    if !description.is_empty() {
+        manual = manual.description(description.join("\n.PP\n"));
+    }
+
     // Assume multiple authors are passed separated by newline. Worst case the
     // formatting comes out slightly different.
-    for authors in &app.author {
+    for authors in &cmd.author {
         for author in authors.split("\n") {
             manual = manual.author(man::Author::new(author));
         }
     }
 
-    for arg in &app.args {
-        if let Some(_index) = arg.index {
-            let mut positional_arg = man::Arg::new(arg.name);
+    let mut positionals = man::Section::new("arguments");
+    for arg in args {
+        if arg.kind == ArgKind::Positional {
+            let mut name = value_name(arg);
+            if !arg.required {
+                name = format!("[{}]", name);
+            }
+            let positional_arg = man::Arg::new(&name);
             manual = manual.arg(positional_arg);
-        } else if arg.is_set(clap::ArgSettings::TakesValue) {
-            let mut opt = man::Opt::new(arg.name);
+
+            let mut paragraph = format!("\\fB{}\\fR", value_name(arg));
+            if let Some(help) = get_help(arg) {
+                paragraph.push_str(&format!("\n{}", help));
+            }
+            positionals = positionals.paragraph(&paragraph);
+        } else if arg.kind == ArgKind::Option {
+            let mut opt = man::Opt::new(&value_name(arg));
             if let Some(help) = get_help(arg) {
                 opt = opt.help(&help);
             }
             if let Some(short) = arg.short {
                 opt = opt.short(&format!("-{}", short.to_string()));
             }
-            if let Some(long) = arg.long {
+            if let Some(ref long) = arg.long {
                 opt = opt.long(&format!("--{}", long));
             }
             manual = manual.option(opt);
@@ -44,22 +280,110 @@ pub fn gen_manuals(app: &clap::App) -> Vec<man::Manual> {
             if let Some(short) = arg.short {
                 flag = flag.short(&format!("-{}", short.to_string()));
             }
-            if let Some(long) = arg.long {
+            if let Some(ref long) = arg.long {
                 flag = flag.long(&format!("--{}", long));
             }
             manual = manual.flag(flag);
         }
     }
 
-    vec![manual]
+    if args.iter().any(|a| a.kind == ArgKind::Positional) {
+        manual = manual.custom(positionals);
+    }
+
+    // Each variable is only documented once, even if several args read it
+    let mut envs: Vec<&str> = vec![];
+    for arg in args {
+        if let Some(ref name) = arg.env {
+            if envs.contains(&&**name) {
+                continue;
+            }
+            envs.push(name);
+            let mut env = man::Env::new(name);
+            if let Some(default) = arg.default_values.first() {
+                env = env.default_value(default);
+            }
+            let switch = match (&arg.long, arg.short) {
+                (&Some(ref long), _) => format!("--{}", long),
+                (&None, Some(short)) => format!("-{}", short),
+                (&None, None) => value_name(arg),
+            };
+            let help = match arg.help {
+                Some(ref help) => format!("Sets {} if not given. {}", switch, help),
+                None => format!("Sets {} if not given.", switch),
+            };
+            manual = manual.env(env.help(&help));
+        }
+    }
+
+    if cmd.has_subcommands() {
+        let mut commands = man::Section::new("commands");
+        for sc in &cmd.subcommands {
+            let mut paragraph = format!("\\fB{}\\fR", sc.name);
+            if let Some(ref about) = sc.about {
+                paragraph.push_str(&format!("\n{}", about));
+            }
+            paragraph.push_str(&format!("\nSee \\fB{}\\fR({}).", page_name(sc), section));
+            commands = commands.paragraph(&paragraph);
+        }
+        manual = manual.custom(commands);
+    }
+
+    if let Some(ref after_help) = cmd.after_help {
+        manual = manual.custom(man::Section::new("notes").paragraph(after_help));
+    }
+
+    let see_also: Vec<String> = parent
+        .into_iter()
+        .chain(&cmd.subcommands)
+        .map(|c| format!("\\fB{}\\fR({})", page_name(c), section))
+        .collect();
+    if !see_also.is_empty() {
+        manual = manual.custom(man::Section::new("see also").paragraph(&see_also.join(", ")));
+    }
+
+    manual
+}
+
+// Gets the help of an arg followed by the details of the values it accepts, i.e.
+// `[possible values: auto, always, never] [default: auto] [env: MYAPP_COLOR]`
+fn get_help(arg: &ArgSpec) -> Option<String> {
+    let mut details = vec![];
+    if !arg.possible_values.is_empty() {
+        details.push(format!("[possible values: {}]", arg.possible_values.join(", ")));
+    }
+    if !arg.default_values.is_empty() {
+        details.push(format!("[default: {}]", arg.default_values.join(", ")));
+    }
+    if let Some(ref env) = arg.env {
+        details.push(format!("[env: {}]", env));
+    }
+    if arg.required && arg.kind != ArgKind::Positional {
+        details.push(String::from("[required]"));
+    }
+
+    let help = arg.long_help.clone().or_else(|| arg.help.clone());
+    match (help, details.is_empty()) {
+        (help, true) => help,
+        (Some(help), false) => Some(format!("{} {}", help, details.join(" "))),
+        (None, false) => Some(details.join(" ")),
+    }
 }
 
-fn get_help(arg: &clap::Arg) -> Option<String> {
-    if let Some(help) = arg.long_help {
-        Some(help.into())
-    } else if let Some(help) = arg.help {
-        Some(help.into())
+// Gets the name of the value(s) of an arg as shown in usage strings, i.e. `<WHEN>` or `<FILE>...`
+fn value_name(arg: &ArgSpec) -> String {
+    let names = if arg.value_names.is_empty() {
+        vec![arg.name.clone()]
     } else {
-        None
+        arg.value_names.clone()
+    };
+    let mut name = names
+        .iter()
+        .map(|n| format!("<{}>", n))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if arg.multiple_values || (arg.kind == ArgKind::Positional && arg.multiple_occurrences) {
+        name.push_str("...");
     }
+    name
 }
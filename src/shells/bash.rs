@@ -2,190 +2,373 @@
 use std::io::Write;
 
 // Internal
-use clap::{App, Arg};
+use errors::Result;
+use shells::Generator;
+use spec::{ArgSpec, CommandSpec, ValueHint};
 
-use shells;
+/// Generates a completion file for the Bourne Again SHell (BASH)
+#[derive(Debug, Copy, Clone)]
+pub struct BashGen;
 
-pub struct BashGen<'a, 'b>(&'b App<'a, 'b>)
-where
-    'a: 'b;
+impl Generator for BashGen {
+    fn file_name(name: &str) -> String { format!("{}.bash", name) }
 
-impl<'a, 'b> BashGen<'a, 'b> {
-    pub fn new(app: &'b App<'a, 'b>) -> Self { BashGen(app) }
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<()> {
+        let mut commands = vec![];
+        commands_of(cmd, cmd.bin_name.clone(), &mut commands);
 
-    pub fn generate_to<W: Write>(&self, buf: &mut W) {
         w!(
             buf,
             format!(
                 "_{name}() {{
-    local i cur prev opts cmds
+    local i cur prev words cword opts cmd skip prefix used pos
     COMPREPLY=()
-    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"
-    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"
-    cmd=\"\"
+    if declare -F _get_comp_words_by_ref >/dev/null 2>&1 ; then
+        _get_comp_words_by_ref -n =: cur prev words cword
+    else
+        cur=\"${{COMP_WORDS[COMP_CWORD]}}\"
+        prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"
+        words=(\"${{COMP_WORDS[@]}}\")
+        cword=${{COMP_CWORD}}
+        if [[ ${{cur}} == \"=\" ]] ; then
+            cur=\"\"
+        elif [[ ${{prev}} == \"=\" ]] ; then
+            prev=\"${{COMP_WORDS[COMP_CWORD-2]}}\"
+        fi
+    fi
+    cmd=\"{name}\"
     opts=\"\"
+    skip=\"\"
+    prefix=\"\"
+    used=\"\"
+    pos=0
 
-    for i in ${{COMP_WORDS[@]}}
+    for i in \"${{words[@]:1:cword-1}}\"
     do
-        case \"${{i}}\" in
-            {name})
-                cmd=\"{name}\"
+        if [[ ${{i}} == \"=\" ]] ; then
+            skip=1
+            continue
+        fi
+        if [[ -n \"${{skip}}\" ]] ; then
+            skip=\"\"
+            continue
+        fi
+        case \"${{cmd}},${{i}}\" in{transitions}
+            *,-*)
+                used+=\" ${{i%%=*}}\"
                 ;;
-            {subcmds}
             *)
+                pos=$((pos + 1))
                 ;;
         esac
     done
 
-    case \"${{cmd}}\" in
-        {name})
-            opts=\"{name_opts}\"
-            if [[ ${{cur}} == -* || ${{COMP_CWORD}} -eq 1 ]] ; then
-                COMPREPLY=( $(compgen -W \"${{opts}}\" -- ${{cur}}) )
-                return 0
-            fi
-            case \"${{prev}}\" in
-                {name_opts_details}
-                *)
-                    COMPREPLY=()
-                    ;;
-            esac
-            COMPREPLY=( $(compgen -W \"${{opts}}\" -- ${{cur}}) )
-            return 0
-            ;;
-        {subcmd_details}
+    if [[ ${{cur}} == --*=* ]] ; then
+        prev=\"${{cur%%=*}}\"
+        cur=\"${{cur#*=}}\"
+        if [[ ${{COMP_WORDBREAKS}} != *=* ]] ; then
+            prefix=\"${{prev}}=\"
+        fi
+    fi
+
+    case \"${{cmd}}\" in{details}
     esac
+
+    if [[ -n \"${{prefix}}\" ]] ; then
+        COMPREPLY=( \"${{COMPREPLY[@]/#/${{prefix}}}}\" )
+    fi
+    if declare -F __ltrim_colon_completions >/dev/null 2>&1 ; then
+        __ltrim_colon_completions \"${{prefix}}${{cur}}\"
+    fi
+    if [[ ${{#COMPREPLY[@]}} -eq 1 && ${{COMPREPLY[0]}} == *= ]] ; then
+        compopt -o nospace 2>/dev/null
+    fi
+    return 0
 }}
 
-complete -F _{name} -o bashdefault -o default {name}
+complete -F _{name} -o bashdefault -o default {bin_names}
 ",
-                name = self.0.bin_name.as_ref().unwrap(),
-                name_opts = self.all_options_for_path(self.0.bin_name.as_ref().unwrap()),
-                name_opts_details = self.option_details_for_path(self.0.bin_name.as_ref().unwrap()),
-                subcmds = self.all_subcommands(),
-                subcmd_details = self.subcommand_details()
+                name = cmd.bin_name,
+                bin_names = cmd.bin_names().join(" "),
+                transitions = transitions(&commands),
+                details = details(&commands)
             ).as_bytes()
         );
+        Ok(())
     }
+}
 
-    fn all_subcommands(&self) -> String {
-        debugln!("BashGen::all_subcommands;");
-        let mut subcmds = String::new();
-        let scs = shells::all_subcommand_names(self.0);
+// Gets every command along with the value `cmd` holds while completing it, i.e.
+// `myapp__remote__add`
+fn commands_of<'a>(
+    p: &'a CommandSpec,
+    path: String,
+    commands: &mut Vec<(String, &'a CommandSpec)>,
+) {
+    commands.push((path.clone(), p));
+    for sc in &p.subcommands {
+        commands_of(sc, format!("{}__{}", path, sc.name.replace("-", "__")), commands);
+    }
+}
 
-        for sc in &scs {
-            subcmds = format!(
-                "{}
-            {name})
-                cmd+=\"__{fn_name}\"
+// Gets the `case`s of the loop walking the words before the one being completed, which move
+// `cmd` to a subcommand (by its name or one of its aliases) only when it's a subcommand of the
+// current command, and skip the values of the options of the current command, so that neither
+// same-named subcommands at other depths nor values which happen to be subcommand names are
+// mistaken for the subcommand being completed
+//
+// Along the way the options of the current command are collected in `used` and its positional
+// arguments are counted in `pos`, both of which start over with each subcommand
+fn transitions(commands: &[(String, &CommandSpec)]) -> String {
+    debugln!("BashGen::transitions;");
+    let mut cases = String::new();
+    for &(ref path, p) in commands {
+        for sc in &p.subcommands {
+            let patterns: Vec<String> = Some(&sc.name)
+                .into_iter()
+                .chain(sc.aliases.iter().map(|a| &a.name))
+                .map(|name| format!("{},{}", path, name))
+                .collect();
+            cases.push_str(&format!(
+                "
+            {patterns})
+                cmd=\"{path}__{fn_name}\"
+                used=\"\"
+                pos=0
                 ;;",
-                subcmds,
-                name = sc,
-                fn_name = sc.replace("-", "__")
-            );
+                patterns = patterns.join("|"),
+                path = path,
+                fn_name = sc.name.replace("-", "__")
+            ));
         }
 
-        subcmds
+        let mut switches = vec![];
+        // Values of options which require `=` or have an optional value are never a separate word
+        for o in p.opts().filter(|o| !o.require_equals && o.min_values != Some(0)) {
+            switches.extend(o.longs().into_iter().map(|l| format!("{},--{}", path, l)));
+            switches.extend(o.short.map(|s| format!("{},-{}", path, s)));
+        }
+        if !switches.is_empty() {
+            cases.push_str(&format!(
+                "
+            {})
+                used+=\" ${{i}}\"
+                skip=1
+                ;;",
+                switches.join("|")
+            ));
+        }
     }
+    cases
+}
 
-    fn subcommand_details(&self) -> String {
-        debugln!("BashGen::subcommand_details;");
-        let mut subcmd_dets = String::new();
-        let mut scs = shells::get_all_subcommand_paths(self.0, true);
-        scs.sort();
-        scs.dedup();
-
-        for sc in &scs {
-            subcmd_dets = format!(
-                "{}
-        {subcmd})
-            opts=\"{sc_opts}\"
-            if [[ ${{cur}} == -* || ${{COMP_CWORD}} -eq {level} ]] ; then
+fn details(commands: &[(String, &CommandSpec)]) -> String {
+    debugln!("BashGen::details;");
+    let mut dets = String::new();
+    for &(ref path, p) in commands {
+        dets = format!(
+            "{}
+        {path})
+            opts=\"{opts}\"{used}{attached}
+            if [[ ${{cur}} == -* ]] ; then
                 COMPREPLY=( $(compgen -W \"${{opts}}\" -- ${{cur}}) )
-                return 0
+            else
+                case \"${{prev}}\" in{opts_details}
+                    *)
+                        COMPREPLY=( $(compgen -W \"${{opts}}\" -- ${{cur}}) ){positionals}
+                        ;;
+                esac
             fi
-            case \"${{prev}}\" in
-                {opts_details}
-                *)
-                    COMPREPLY=()
-                    ;;
-            esac
-            COMPREPLY=( $(compgen -W \"${{opts}}\" -- ${{cur}}) )
-            return 0
             ;;",
-                subcmd_dets,
-                subcmd = sc.replace("-", "__"),
-                sc_opts = self.all_options_for_path(&*sc),
-                level = sc.split("__").map(|_| 1).fold(0, |acc, n| acc + n),
-                opts_details = self.option_details_for_path(&*sc)
-            );
-        }
-
-        subcmd_dets
+            dets,
+            path = path,
+            opts = all_options_for(p),
+            used = used_options_for(p),
+            attached = attached_values_for(p),
+            opts_details = option_details_for(p),
+            positionals = positional_values_for(p)
+        );
     }
+    dets
+}
 
-    fn option_details_for_path(&self, path: &str) -> String {
-        debugln!("BashGen::option_details_for_path: path={}", path);
-        let mut p = self.0;
-        for sc in path.split("__").skip(1) {
-            debugln!("BashGen::option_details_for_path:iter: sc={}", sc);
-            p = &find_subcmd!(p, sc).unwrap();
+// Gets the loop removing the options which may only be given once from `opts` when they're
+// already on the command line
+fn used_options_for(p: &CommandSpec) -> String {
+    let mut cases = String::new();
+    for arg in p.flags().chain(p.opts()).filter(|a| !a.multiple_occurrences) {
+        let switches = arg.short
+            .map(|s| format!("-{}", s))
+            .into_iter()
+            .chain(arg.longs().into_iter().map(|l| format!("--{}", l)))
+            .collect::<Vec<_>>();
+        if switches.is_empty() {
+            continue;
         }
-        let mut opts = String::new();
-        for o in opts!(p) {
-            if let Some(l) = o.long {
-                opts = format!(
-                    "{}
-                --{})
-                    COMPREPLY=({})
-                    return 0
-                    ;;",
-                    opts,
-                    l,
-                    self.vals_for(o)
-                );
-            }
-            if let Some(s) = o.short {
-                opts = format!(
-                    "{}
-                    -{})
-                    COMPREPLY=({})
-                    return 0
-                    ;;",
-                    opts,
-                    s,
-                    self.vals_for(o)
-                );
-            }
+        cases.push_str(&format!("\n                    {})", switches.join("|")));
+        for word in arg.short.map(|s| format!("-{}", s)).into_iter().chain(long_words(arg)) {
+            cases.push_str(&format!("\n                        opts=\"${{opts/ {} / }}\"", word));
         }
-        opts
+        cases.push_str("\n                        ;;");
+    }
+    if cases.is_empty() {
+        return cases;
     }
+    format!(
+        "
+            for i in ${{used}}
+            do
+                case \"${{i}}\" in{}
+                esac
+            done",
+        cases
+    )
+}
 
-    fn vals_for(&self, o: &Arg) -> String {
-        debugln!("BashGen::vals_for: o={}", o.name);
-        if let Some(ref vals) = o.possible_vals {
-            format!("$(compgen -W \"{}\" -- ${{cur}})", vals.join(" "))
-        } else {
-            String::from("$(compgen -f ${cur})")
+// Gets the `case` completing the positional argument at the position `pos`, where a variadic
+// last positional argument completes all remaining words
+fn positional_values_for(p: &CommandSpec) -> String {
+    let mut positionals: Vec<&ArgSpec> = p.positionals().collect();
+    positionals.sort_by_key(|a| a.index);
+    let mut arms = String::new();
+    for (i, arg) in positionals.iter().enumerate() {
+        let vals = vals_for(arg);
+        if vals.is_empty() && !excludes_files(arg) {
+            continue;
         }
+        let last = i + 1 == positionals.len();
+        let pattern = if last && (arg.multiple_values || arg.multiple_occurrences) {
+            String::from("*")
+        } else {
+            i.to_string()
+        };
+        let compopt = if excludes_files(arg) {
+            "\n                                compopt +o default +o bashdefault 2>/dev/null"
+        } else {
+            ""
+        };
+        arms.push_str(&format!(
+            "
+                            {}){}
+                                COMPREPLY+=({})
+                                ;;",
+            pattern, compopt, vals
+        ));
+    }
+    if arms.is_empty() {
+        return arms;
     }
+    format!(
+        "
+                        case \"${{pos}}\" in{}
+                        esac",
+        arms
+    )
+}
 
-    fn all_options_for_path(&self, path: &str) -> String {
-        debugln!("BashGen::all_options_for_path: path={}", path);
-        let mut p = self.0;
-        for sc in path.split("__").skip(1) {
-            debugln!("BashGen::all_options_for_path:iter: sc={}", sc);
-            p = &find_subcmd!(p, sc).unwrap();
-        }
-        let opts = format!(
-            "{shorts} {longs} {pos} {subcmds}",
-            shorts = shorts!(p).fold(String::new(), |acc, s| format!("{} -{}", acc, s)),
-            // Handles aliases too
-            longs = longs!(p).fold(String::new(), |acc, l| format!("{} --{}", acc, l)),
-            pos = positionals!(p).fold(String::new(), |acc, p| format!("{} {}", acc, p)),
-            // Handles aliases too
-            subcmds = sc_names!(p).fold(String::new(), |acc, s| format!("{} {}", acc, s))
+// Gets the `case` splitting a value attached to a short option (i.e. `-ofile`) off the current
+// word, so that it's completed like a separate value which keeps the option as its prefix
+fn attached_values_for(p: &CommandSpec) -> String {
+    let patterns: Vec<String> = p.opts()
+        .filter(|o| !o.require_equals)
+        .filter_map(|o| o.short)
+        .map(|s| format!("-{}?*", s))
+        .collect();
+    if patterns.is_empty() {
+        return String::new();
+    }
+    format!(
+        "
+            case \"${{cur}}\" in
+                {})
+                    prev=\"${{cur:0:2}}\"
+                    prefix=\"${{prev}}\"
+                    cur=\"${{cur:2}}\"
+                    ;;
+            esac",
+        patterns.join("|")
+    )
+}
+
+fn option_details_for(p: &CommandSpec) -> String {
+    debugln!("BashGen::option_details_for: name={}", p.name);
+    let mut opts = String::new();
+    for o in p.opts() {
+        let switches = o.longs()
+            .into_iter()
+            .map(|l| format!("--{}", l))
+            .chain(o.short.map(|s| format!("-{}", s)))
+            .collect::<Vec<_>>();
+        let compopt = if excludes_files(o) {
+            "\n                        compopt +o default +o bashdefault 2>/dev/null"
+        } else {
+            ""
+        };
+        opts = format!(
+            "{}
+                    {}){}
+                        COMPREPLY=({})
+                        ;;",
+            opts,
+            switches.join("|"),
+            compopt,
+            vals_for(o)
         );
-        opts
     }
+    opts
+}
+
+fn vals_for(o: &ArgSpec) -> String {
+    debugln!("BashGen::vals_for: o={}", o.name);
+    if !o.possible_values.is_empty() {
+        return format!("$(compgen -W \"{}\" -- ${{cur}})", o.possible_values.join(" "));
+    }
+    match o.value_hint {
+        ValueHint::DirPath => String::from("$(compgen -d ${cur})"),
+        ValueHint::CommandName => String::from("$(compgen -c ${cur})"),
+        ValueHint::Username => String::from("$(compgen -u ${cur})"),
+        ValueHint::Hostname => String::from("$(compgen -A hostname ${cur})"),
+        ValueHint::Url | ValueHint::Other => String::new(),
+        ValueHint::Unknown
+        | ValueHint::AnyPath
+        | ValueHint::FilePath
+        | ValueHint::ExecutablePath => String::from("$(compgen -f ${cur})"),
+    }
+}
+
+// Whether the values of `o` are never files, so that bash mustn't fall back to completing
+// filenames (`-o default`) when there are no matches
+fn excludes_files(o: &ArgSpec) -> bool {
+    o.possible_values.is_empty() && match o.value_hint {
+        ValueHint::CommandName
+        | ValueHint::Username
+        | ValueHint::Hostname
+        | ValueHint::Url
+        | ValueHint::Other => true,
+        _ => false,
+    }
+}
+
+// Gets the words completing the long switches of `arg`, where options which require `=` are
+// completed with it so that the value can follow right away
+fn long_words(arg: &ArgSpec) -> Vec<String> {
+    arg.longs()
+        .into_iter()
+        .map(|l| if arg.require_equals { format!("--{}=", l) } else { format!("--{}", l) })
+        .collect()
+}
+
+// Gets the options and subcommands of `p`, each of which is surrounded by spaces so that it can
+// be removed again
+fn all_options_for(p: &CommandSpec) -> String {
+    debugln!("BashGen::all_options_for: name={}", p.name);
+    let mut words: Vec<String> = p.args
+        .iter()
+        .filter_map(|a| a.short)
+        .map(|s| format!("-{}", s))
+        .collect();
+    words.extend(p.args.iter().flat_map(long_words));
+    // Handles aliases too
+    words.extend(p.subcommand_names().into_iter().map(String::from));
+    format!(" {} ", words.join(" "))
 }
@@ -0,0 +1,156 @@
+// Std
+use std::io::Write;
+
+// Third Party
+use serde_json;
+
+// Internal
+use errors::Result;
+use shells::Generator;
+use spec::{ArgKind, ArgSpec, CommandSpec, ValueHint};
+use INTERNAL_ERROR_MSG;
+
+/// Generates a spec for carapace, which bridges the completions to every shell it supports
+#[derive(Debug, Copy, Clone)]
+pub struct CarapaceGen;
+
+impl Generator for CarapaceGen {
+    fn file_name(name: &str) -> String { format!("{}.yaml", name) }
+
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<()> {
+        let mut lines = vec![];
+        command_of(cmd, &cmd.bin_name, &mut lines);
+        let mut result = lines.join("\n");
+        result.push('\n');
+
+        w!(buf, result.as_bytes());
+        Ok(())
+    }
+}
+
+// YAML is a superset of JSON, so a JSON string is always a valid (double quoted) YAML scalar
+fn quote(s: &str) -> String { serde_json::to_string(s).expect(INTERNAL_ERROR_MSG) }
+
+fn list(values: &[String]) -> String {
+    format!(
+        "[{}]",
+        values.iter().map(|v| quote(v)).collect::<Vec<_>>().join(", ")
+    )
+}
+
+// Gets the key of an arg in `flags`, i.e. `-c, --case=`, or `None` if it isn't a switch
+fn flag_of(arg: &ArgSpec) -> Option<String> {
+    let names: Vec<String> = arg
+        .short
+        .iter()
+        .map(|s| format!("-{}", s))
+        .chain(arg.long.iter().map(|l| format!("--{}", l)))
+        .collect();
+    if names.is_empty() {
+        return None;
+    }
+    let mut flag = names.join(", ");
+    match arg.kind {
+        ArgKind::Flag => (),
+        ArgKind::Option => flag.push(if arg.min_values == Some(0) { '?' } else { '=' }),
+        ArgKind::Positional => return None,
+    }
+    if arg.multiple_occurrences {
+        flag.push('*');
+    }
+    if arg.required {
+        flag.push('!');
+    }
+    Some(flag)
+}
+
+// Gets the completion of the values of an arg, using the carapace macros for paths
+fn values_of(arg: &ArgSpec) -> Option<Vec<String>> {
+    if !arg.possible_values.is_empty() {
+        return Some(arg.possible_values.clone());
+    }
+    match arg.value_hint {
+        ValueHint::AnyPath | ValueHint::FilePath | ValueHint::ExecutablePath => {
+            Some(vec!["$files".into()])
+        }
+        ValueHint::DirPath => Some(vec!["$directories".into()]),
+        _ => None,
+    }
+}
+
+// Writes the lines describing `p`, the first of which is the `name` and the rest of which are
+// indented to line up with it
+fn command_of(p: &CommandSpec, name: &str, lines: &mut Vec<String>) {
+    debugln!("CarapaceGen::command_of: name={}", name);
+    lines.push(format!("name: {}", quote(name)));
+    if !p.aliases.is_empty() {
+        let aliases: Vec<String> = p.aliases.iter().map(|a| a.name.clone()).collect();
+        lines.push(format!("aliases: {}", list(&aliases)));
+    }
+    if let Some(ref about) = p.about {
+        lines.push(format!("description: {}", quote(about)));
+    }
+
+    let switches: Vec<(&ArgSpec, String)> = p.flags()
+        .chain(p.opts())
+        .filter_map(|a| flag_of(a).map(|flag| (a, flag)))
+        .collect();
+    if !switches.is_empty() {
+        lines.push(String::from("flags:"));
+        for &(arg, ref flag) in &switches {
+            lines.push(format!(
+                "  {}: {}",
+                quote(flag),
+                quote(arg.help.as_ref().map_or("", |s| &**s))
+            ));
+        }
+    }
+
+    let mut flag_values = vec![];
+    for arg in switches.iter().map(|&(a, _)| a).filter(|a| a.takes_value()) {
+        if let Some(values) = values_of(arg) {
+            let name = match arg.long {
+                Some(ref l) => l.clone(),
+                None => arg.short.expect(INTERNAL_ERROR_MSG).to_string(),
+            };
+            flag_values.push(format!("    {}: {}", quote(&name), list(&values)));
+        }
+    }
+    // A variadic positional has to be the last one, and completes all remaining words
+    let mut positional = vec![];
+    let mut positional_any = None;
+    for arg in p.positionals() {
+        let values = values_of(arg).unwrap_or_else(|| vec!["$files".into()]);
+        if arg.multiple_values || arg.multiple_occurrences {
+            positional_any = Some(values);
+        } else {
+            positional.push(format!("    - {}", list(&values)));
+        }
+    }
+    if !flag_values.is_empty() || !positional.is_empty() || positional_any.is_some() {
+        lines.push(String::from("completion:"));
+        if !flag_values.is_empty() {
+            lines.push(String::from("  flag:"));
+            lines.extend(flag_values);
+        }
+        if !positional.is_empty() {
+            lines.push(String::from("  positional:"));
+            lines.extend(positional);
+        }
+        if let Some(values) = positional_any {
+            lines.push(format!("  positionalany: {}", list(&values)));
+        }
+    }
+
+    if p.has_subcommands() {
+        lines.push(String::from("commands:"));
+        for sc in &p.subcommands {
+            let mut sc_lines = vec![];
+            command_of(sc, &sc.name, &mut sc_lines);
+            for (i, line) in sc_lines.into_iter().enumerate() {
+                let prefix = if i == 0 { "  - " } else { "    " };
+                lines.push(format!("{}{}", prefix, line));
+            }
+        }
+    }
+}
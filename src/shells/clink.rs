@@ -0,0 +1,165 @@
+// Std
+use std::io::Write;
+
+// Internal
+use errors::Result;
+use shells::{self, Generator};
+use spec::{ArgSpec, CommandSpec, ValueHint};
+
+/// Generates a Lua argmatcher script for Clink (cmd.exe)
+#[derive(Debug, Copy, Clone)]
+pub struct ClinkGen;
+
+impl Generator for ClinkGen {
+    fn file_name(name: &str) -> String { format!("{}.lua", name) }
+
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<()> {
+        let mut result = String::new();
+        if cmd.has_subcommands() {
+            result.push_str("local m = {}\n");
+        }
+        for sc in &cmd.subcommands {
+            result.push_str(&generate_inner(sc));
+        }
+
+        result.push_str(&format!(
+            "clink.argmatcher({}){}",
+            cmd.bin_names()
+                .iter()
+                .map(|n| quote(n))
+                .collect::<Vec<_>>()
+                .join(", "),
+            matcher_of(cmd)
+        ));
+
+        w!(buf, result.as_bytes());
+        Ok(())
+    }
+}
+
+// Gets a Lua string literal of `string`
+fn quote(string: &str) -> String {
+    format!(
+        "\"{}\"",
+        string
+            .replace("\\", "\\\\")
+            .replace("\"", "\\\"")
+            .replace("\n", "\\n")
+    )
+}
+
+// Gets the entry of the `m` table holding the argmatcher of a subcommand, i.e.
+// `m["myapp remote add"]`, keyed by the full bin name so that no two subcommands can collide and
+// a large tree doesn't run into Lua's limit of 200 locals
+fn matcher_ref(p: &CommandSpec) -> String { format!("m[{}]", quote(&p.bin_name)) }
+
+// Defines the argmatchers of `p` and all of its subcommands, innermost first so that each one is
+// defined before it's linked to its parent
+fn generate_inner(p: &CommandSpec) -> String {
+    debugln!("ClinkGen::generate_inner: name={}", p.name);
+    let mut ret = String::new();
+    for sc in &p.subcommands {
+        ret.push_str(&generate_inner(sc));
+    }
+    ret.push_str(&format!(
+        "{} = clink.argmatcher(){}\n",
+        matcher_ref(p),
+        matcher_of(p)
+    ));
+    ret
+}
+
+// Gets the entries of the `:addarg` table matching the values of an option (or positional
+// argument): either its possible values or a match generator for paths
+fn entries_of(arg: &ArgSpec) -> Vec<String> {
+    if !arg.possible_values.is_empty() {
+        return arg.possible_values.iter().map(|v| quote(v)).collect();
+    }
+    match arg.value_hint {
+        ValueHint::DirPath => vec![String::from("clink.dirmatches")],
+        ValueHint::Unknown
+        | ValueHint::AnyPath
+        | ValueHint::FilePath
+        | ValueHint::ExecutablePath => vec![String::from("clink.filematches")],
+        _ => vec![],
+    }
+}
+
+// Gets the matcher of the values of an option (or positional argument)
+fn values_of(arg: &ArgSpec) -> String {
+    let entries = entries_of(arg);
+    match entries.len() {
+        0 => String::from("{}"),
+        1 if arg.possible_values.is_empty() => entries[0].clone(),
+        _ => format!("{{ {} }}", entries.join(", ")),
+    }
+}
+
+// Gets the chained calls configuring the argmatcher of `p`
+fn matcher_of(p: &CommandSpec) -> String {
+    let mut args = vec![];
+    let mut descriptions = vec![];
+
+    let mut positionals = p.positionals();
+    if p.has_subcommands() {
+        // The first word is either a subcommand or the value of the first positional argument
+        let mut entries = vec![];
+        for sc in &p.subcommands {
+            for name in Some(&sc.name)
+                .into_iter()
+                .chain(sc.aliases.iter().map(|a| &a.name))
+            {
+                entries.push(format!("{} .. {}", quote(name), matcher_ref(sc)));
+            }
+            if let Some(ref about) = sc.about {
+                descriptions.push((sc.name.clone(), about.clone()));
+            }
+        }
+        if let Some(arg) = positionals.next() {
+            entries.extend(entries_of(arg));
+        }
+        args.push(format!("\n    :addarg({{\n        {},\n    }})", entries.join(",\n        ")));
+    }
+    for arg in positionals {
+        args.push(format!("\n    :addarg({})", values_of(arg)));
+    }
+
+    let mut flags = vec![];
+    for arg in p.flags().chain(p.opts()) {
+        let help = shells::help_with_heading(arg);
+        let switches = arg.short
+            .map(|s| format!("-{}", s))
+            .into_iter()
+            .chain(arg.longs().into_iter().map(|l| format!("--{}", l)));
+        for switch in switches {
+            if arg.takes_value() {
+                flags.push(format!(
+                    "{} .. clink.argmatcher():addarg({})",
+                    quote(&switch),
+                    values_of(arg)
+                ));
+            } else {
+                flags.push(quote(&switch));
+            }
+            if let Some(ref help) = help {
+                descriptions.push((switch, help.clone()));
+            }
+        }
+    }
+    if !flags.is_empty() {
+        args.push(format!("\n    :addflags({{\n        {},\n    }})", flags.join(",\n        ")));
+    }
+
+    if !descriptions.is_empty() {
+        args.push(format!(
+            "\n    :adddescriptions({{\n        {},\n    }})",
+            descriptions
+                .iter()
+                .map(|&(ref name, ref desc)| format!("[{}] = {}", quote(name), quote(desc)))
+                .collect::<Vec<_>>()
+                .join(",\n        ")
+        ));
+    }
+    args.push(String::from("\n"));
+    args.concat()
+}
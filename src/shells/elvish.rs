@@ -2,27 +2,38 @@
 use std::io::Write;
 
 // Internal
-use clap::App;
-use INTERNAL_ERROR_MSG;
-
-pub struct ElvishGen<'a, 'b, 'c>
-where
-    'a: 'b,
-    'b: 'c,
-{
-    app: &'c App<'a, 'b>,
-}
-
-impl<'a, 'b, 'c> ElvishGen<'a, 'b, 'c> {
-    pub fn new(p: &'c App<'a, 'b>) -> Self { ElvishGen { app: p } }
-
-    pub fn generate_to<W: Write>(&self, buf: &mut W) {
-        let bin_name = self.app.bin_name.as_ref().unwrap();
-
-        let mut names = vec![];
-        let subcommands_cases = generate_inner(self.app, "", &mut names);
-
-        let result = format!(
+use errors::Result;
+use shells::Generator;
+use spec::{CommandSpec, ValueHint};
+
+/// Generates a completion file for Elvish
+#[derive(Debug, Copy, Clone)]
+pub struct ElvishGen;
+
+impl Generator for ElvishGen {
+    fn file_name(name: &str) -> String { format!("{}.elv", name) }
+
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<()> {
+        let bin_name = &cmd.bin_name;
+        let subcommands_cases = generate_inner(cmd, "");
+        let value_hints = generate_value_hints(cmd, "");
+        let lookup = if value_hints.is_empty() {
+            String::from("$completions[$command]")
+        } else {
+            format!(
+                r"values = [{}
+    ]
+    key = $command';'$words[-2]
+    if (has-key $values $key) {{
+        $values[$key]
+    }} else {{
+        $completions[$command]
+    }}",
+                value_hints
+            )
+        };
+
+        let mut result = format!(
             r#"
 edit:completion:arg-completer[{bin_name}] = [@words]{{
     fn spaces [n]{{
@@ -40,14 +51,22 @@ edit:completion:arg-completer[{bin_name}] = [@words]{{
     }}
     completions = [{subcommands_cases}
     ]
-    $completions[$command]
+    {lookup}
 }}
 "#,
             bin_name = bin_name,
-            subcommands_cases = subcommands_cases
+            subcommands_cases = subcommands_cases,
+            lookup = lookup
         );
+        for alias in &cmd.bin_aliases {
+            result.push_str(&format!(
+                "edit:completion:arg-completer[{}] = $edit:completion:arg-completer[{}]\n",
+                alias, bin_name
+            ));
+        }
 
         w!(buf, result.as_bytes());
+        Ok(())
     }
 }
 
@@ -61,18 +80,10 @@ fn get_tooltip<T: ToString>(help: Option<&str>, data: T) -> String {
     }
 }
 
-fn generate_inner<'a, 'b, 'c>(
-    p: &'c App<'a, 'b>,
-    previous_command_name: &str,
-    names: &mut Vec<&'a str>,
-) -> String
-where
-    'a: 'b,
-    'b: 'c,
-{
+fn generate_inner(p: &CommandSpec, previous_command_name: &str) -> String {
     debugln!("ElvishGen::generate_inner;");
     let command_name = if previous_command_name.is_empty() {
-        p.bin_name.as_ref().expect(INTERNAL_ERROR_MSG).clone()
+        p.bin_name.clone()
     } else {
         format!("{};{}", previous_command_name, &p.name)
     };
@@ -80,27 +91,27 @@ where
     let mut completions = String::new();
     let preamble = String::from("\n            cand ");
 
-    for option in opts!(p) {
+    for option in p.opts() {
         if let Some(data) = option.short {
-            let tooltip = get_tooltip(option.help, data);
+            let tooltip = get_tooltip(option.help.as_ref().map(|s| &**s), data);
             completions.push_str(&preamble);
             completions.push_str(format!("-{} '{}'", data, tooltip).as_str());
         }
-        if let Some(data) = option.long {
-            let tooltip = get_tooltip(option.help, data);
+        if let Some(ref data) = option.long {
+            let tooltip = get_tooltip(option.help.as_ref().map(|s| &**s), data);
             completions.push_str(&preamble);
             completions.push_str(format!("--{} '{}'", data, tooltip).as_str());
         }
     }
 
-    for flag in flags!(p) {
+    for flag in p.flags() {
         if let Some(data) = flag.short {
-            let tooltip = get_tooltip(flag.help, data);
+            let tooltip = get_tooltip(flag.help.as_ref().map(|s| &**s), data);
             completions.push_str(&preamble);
             completions.push_str(format!("-{} '{}'", data, tooltip).as_str());
         }
-        if let Some(data) = flag.long {
-            let tooltip = get_tooltip(flag.help, data);
+        if let Some(ref data) = flag.long {
+            let tooltip = get_tooltip(flag.help.as_ref().map(|s| &**s), data);
             completions.push_str(&preamble);
             completions.push_str(format!("--{} '{}'", data, tooltip).as_str());
         }
@@ -108,7 +119,7 @@ where
 
     for subcommand in &p.subcommands {
         let data = &subcommand.name;
-        let tooltip = get_tooltip(subcommand.about, data);
+        let tooltip = get_tooltip(subcommand.about.as_ref().map(|s| &**s), data);
         completions.push_str(&preamble);
         completions.push_str(format!("{} '{}'", data, tooltip).as_str());
     }
@@ -121,9 +132,46 @@ where
     );
 
     for subcommand in &p.subcommands {
-        let subcommand_subcommands_cases = generate_inner(&subcommand, &command_name, names);
+        let subcommand_subcommands_cases = generate_inner(&subcommand, &command_name);
         subcommands_cases.push_str(&subcommand_subcommands_cases);
     }
 
     subcommands_cases
 }
+
+// Generates the completers for the values of options with a value hint, keyed by the command and
+// the option before the word being completed
+fn generate_value_hints(p: &CommandSpec, previous_command_name: &str) -> String {
+    debugln!("ElvishGen::generate_value_hints;");
+    let command_name = if previous_command_name.is_empty() {
+        p.bin_name.clone()
+    } else {
+        format!("{};{}", previous_command_name, &p.name)
+    };
+
+    let mut cases = String::new();
+    for option in p.opts() {
+        if option.value_hint == ValueHint::Unknown || !option.possible_values.is_empty() {
+            continue;
+        }
+        // Hints without a native completion keep the default completions of the command
+        let action = match option.value_hint {
+            ValueHint::AnyPath
+            | ValueHint::FilePath
+            | ValueHint::DirPath
+            | ValueHint::ExecutablePath => "edit:complete-filename $words[-1]",
+            _ => continue,
+        };
+        if let Some(short) = option.short {
+            cases.push_str(&format!("\n        &'{};-{}'= {{ {} }}", command_name, short, action));
+        }
+        if let Some(ref long) = option.long {
+            cases.push_str(&format!("\n        &'{};--{}'= {{ {} }}", command_name, long, action));
+        }
+    }
+
+    for subcommand in &p.subcommands {
+        cases.push_str(&generate_value_hints(subcommand, &command_name));
+    }
+    cases
+}
@@ -0,0 +1,175 @@
+// Std
+use std::io::Write;
+
+// Third Party
+use serde_json::{self, Value};
+
+// Internal
+use errors::Result;
+use shells::Generator;
+use spec::{ArgKind, ArgSpec, CommandSpec, ValueHint};
+use INTERNAL_ERROR_MSG;
+
+/// Generates a completion spec for Fig (Amazon Q) and inshellisense
+///
+/// The spec is written as a TypeScript module, which is the format expected by the
+/// `withfig/autocomplete` repository; its body is plain JSON.
+#[derive(Debug, Copy, Clone)]
+pub struct FigGen;
+
+impl Generator for FigGen {
+    fn file_name(name: &str) -> String { format!("{}.ts", name) }
+
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<()> {
+        let spec = serde_json::to_string_pretty(&FigCommand::new(cmd, true))
+            .expect(INTERNAL_ERROR_MSG);
+        let result = format!(
+            "const completion: Fig.Spec = {};\n\nexport default completion;\n",
+            spec
+        );
+
+        w!(buf, result.as_bytes());
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct FigCommand<'a> {
+    name: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    subcommands: Vec<FigCommand<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    options: Vec<FigOption<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    args: Vec<FigArg<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FigOption<'a> {
+    name: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "is_false")]
+    is_repeatable: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    is_required: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    is_persistent: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    exclusive_on: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args: Option<FigArg<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FigArg<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "is_false")]
+    is_optional: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    is_variadic: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    suggestions: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template: Option<&'static str>,
+}
+
+fn is_false(b: &bool) -> bool { !*b }
+
+// A single name is written as a string, several as an array
+fn names(mut names: Vec<String>) -> Value {
+    if names.len() == 1 {
+        Value::String(names.remove(0))
+    } else {
+        Value::from(names)
+    }
+}
+
+// Gets the switches of an arg, i.e. `-c` and `--case`
+fn switches(arg: &ArgSpec) -> Vec<String> {
+    arg.short
+        .map(|s| format!("-{}", s))
+        .into_iter()
+        .chain(arg.longs().into_iter().map(|l| format!("--{}", l)))
+        .collect()
+}
+
+impl<'a> FigCommand<'a> {
+    fn new(p: &'a CommandSpec, root: bool) -> Self {
+        debugln!("FigGen::FigCommand::new: name={}", p.name);
+        let name = if root {
+            p.bin_name.clone()
+        } else {
+            p.name.clone()
+        };
+        FigCommand {
+            name: names(
+                Some(name)
+                    .into_iter()
+                    .chain(p.aliases.iter().map(|a| a.name.clone()))
+                    .collect(),
+            ),
+            description: p.about.as_ref().map(|s| &**s),
+            subcommands: p.subcommands.iter().map(|sc| FigCommand::new(sc, false)).collect(),
+            options: p.flags()
+                .chain(p.opts())
+                .filter(|a| a.short.is_some() || a.long.is_some())
+                .map(|a| FigOption::new(p, a))
+                .collect(),
+            args: p.positionals().map(FigArg::new).collect(),
+        }
+    }
+}
+
+impl<'a> FigOption<'a> {
+    fn new(p: &'a CommandSpec, arg: &'a ArgSpec) -> Self {
+        FigOption {
+            name: names(switches(arg)),
+            description: arg.help.as_ref().map(|s| &**s),
+            is_repeatable: arg.multiple_occurrences,
+            is_required: arg.required,
+            is_persistent: arg.global,
+            // Conflicts go both ways, but are only declared on one of the args
+            exclusive_on: p.args
+                .iter()
+                .filter(|a| arg.conflicts.contains(&a.name) || a.conflicts.contains(&arg.name))
+                .flat_map(switches)
+                .collect(),
+            args: if arg.takes_value() {
+                Some(FigArg::new(arg))
+            } else {
+                None
+            },
+        }
+    }
+}
+
+impl<'a> FigArg<'a> {
+    fn new(arg: &'a ArgSpec) -> Self {
+        let positional = arg.kind == ArgKind::Positional;
+        FigArg {
+            name: arg.value_names.first().unwrap_or(&arg.name),
+            description: if positional {
+                arg.help.as_ref().map(|s| &**s)
+            } else {
+                None
+            },
+            is_optional: positional && !arg.required,
+            is_variadic: arg.multiple_values || (positional && arg.multiple_occurrences),
+            suggestions: arg.possible_values.iter().map(|v| &**v).collect(),
+            template: match arg.value_hint {
+                ValueHint::AnyPath | ValueHint::FilePath | ValueHint::ExecutablePath => {
+                    Some("filepaths")
+                }
+                ValueHint::DirPath => Some("folders"),
+                _ => None,
+            },
+        }
+    }
+}
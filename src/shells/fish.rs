@@ -2,27 +2,119 @@
 use std::io::Write;
 
 // Internal
-use clap::App;
+use errors::Result;
+use shells::{self, Generator};
+use spec::{ArgSpec, CommandSpec, ValueHint};
 
-pub struct FishGen<'a, 'b>(&'b App<'a, 'b>)
-where
-    'a: 'b;
+/// Generates a .fish completion file for the Friendly Interactive SHell (fish)
+#[derive(Debug, Copy, Clone)]
+pub struct FishGen;
 
-impl<'a, 'b> FishGen<'a, 'b> {
-    pub fn new(app: &'b App<'a, 'b>) -> Self { FishGen(app) }
+impl Generator for FishGen {
+    fn file_name(name: &str) -> String { format!("{}.fish", name) }
 
-    pub fn generate_to<W: Write>(&self, buf: &mut W) {
-        let command = self.0.bin_name.as_ref().unwrap();
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<()> {
+        let command = &*cmd.bin_name;
         let mut buffer = String::new();
-        gen_fish_inner(command, self, command, &mut buffer);
+        if cmd.has_subcommands() {
+            gen_fish_functions(cmd, &mut buffer);
+        }
+        gen_fish_inner(command, cmd, "", &mut buffer);
+        for alias in &cmd.bin_aliases {
+            buffer.push_str(&format!("complete -c {} -w {}\n", alias, command));
+        }
         w!(buf, buffer.as_bytes());
+        Ok(())
     }
 }
 
 // Escape string inside single quotes
 fn escape_string(string: &str) -> String { string.replace("\\", "\\\\").replace("'", "\\'") }
 
-fn gen_fish_inner(root_command: &str, comp_gen: &FishGen, subcommand: &str, buffer: &mut String) {
+// Gets the prefix of the helper functions of the binary `name`
+fn function_prefix(name: &str) -> String { format!("__fish_{}", shells::identifier(name)) }
+
+// Writes the functions telling which subcommand is being completed, by the full path of
+// subcommand names from the root (i.e. `remote add`), so that nested subcommands with the same
+// name don't get each other's completions. The values of options and everything after `--` are
+// skipped, so a value which happens to be the name of a subcommand doesn't count as one.
+fn gen_fish_functions(cmd: &CommandSpec, buffer: &mut String) {
+    debugln!("FishGen::gen_fish_functions;");
+    let prefix = function_prefix(&cmd.bin_name);
+    let mut cases = String::new();
+    gen_path_cases(cmd, "", &mut cases);
+    buffer.push_str(&format!(
+        r#"function {prefix}_subcommand_path
+    set -l cmd (commandline -opc)
+    set -e cmd[1]
+    set -l path
+    set -l skip 0
+    for word in $cmd
+        if test $skip = 1
+            set skip 0
+            continue
+        end
+        switch "$path;$word"
+            case '*;--'
+                break{cases}
+        end
+    end
+    echo $path
+end
+
+function {prefix}_using_subcommand
+    set -l path ({prefix}_subcommand_path)
+    test "$path" = "$argv"
+end
+
+"#,
+        prefix = prefix,
+        cases = cases
+    ));
+}
+
+// Gets the `case`s of the subcommands of `p`, which move from the `path` of `p` to the path of the
+// subcommand, whether it's typed by its name or one of its aliases, preceded by those of the
+// options of `p` which skip their value (`--opt=value` and `-ovalue` are a single word)
+fn gen_path_cases(p: &CommandSpec, path: &str, cases: &mut String) {
+    let switches: Vec<String> = p
+        .opts()
+        .flat_map(|o| {
+            o.longs()
+                .into_iter()
+                .map(|l| format!("--{}", l))
+                .chain(o.short.map(|s| format!("-{}", s)))
+                .collect::<Vec<_>>()
+        })
+        .map(|switch| format!("'{};{}'", escape_string(path), escape_string(&switch)))
+        .collect();
+    if !switches.is_empty() {
+        cases.push_str(&format!(
+            "\n            case {}\n                set skip 1",
+            switches.join(" ")
+        ));
+    }
+    for sc in &p.subcommands {
+        let sc_path = if path.is_empty() {
+            sc.name.clone()
+        } else {
+            format!("{} {}", path, sc.name)
+        };
+        let patterns: Vec<String> = Some(&sc.name)
+            .into_iter()
+            .chain(sc.aliases.iter().map(|a| &a.name))
+            .map(|name| format!("'{};{}'", escape_string(path), escape_string(name)))
+            .collect();
+        cases.push_str(&format!(
+            "\n            case {}\n                set path '{}'",
+            patterns.join(" "),
+            escape_string(&sc_path)
+        ));
+        gen_path_cases(sc, &sc_path, cases);
+    }
+}
+
+fn gen_fish_inner(root_command: &str, cmd: &CommandSpec, path: &str, buffer: &mut String) {
     debugln!("FishGen::gen_fish_inner;");
     // example :
     //
@@ -34,63 +126,166 @@ fn gen_fish_inner(root_command: &str, comp_gen: &FishGen, subcommand: &str, buff
     //      -a "{possible_arguments}"
     //      -r # if require parameter
     //      -f # don't use file completion
-    //      -n "__fish_use_subcommand"               # complete for command "myprog"
-    //      -n "__fish_seen_subcommand_from subcmd1" # complete for command "myprog subcmd1"
+    //      -x # both of the above, if the parameter isn't a file
+    //      -n "__fish_myprog_using_subcommand"         # complete for command "myprog"
+    //      -n "__fish_myprog_using_subcommand sub sub2" # complete for command "myprog sub sub2"
 
-    let mut basic_template = format!("complete -c {} -n ", root_command);
-    if root_command == subcommand {
-        basic_template.push_str("\"__fish_use_subcommand\"");
+    let condition = if cmd.has_subcommands() || !path.is_empty() {
+        let mut condition = format!("{}_using_subcommand", function_prefix(root_command));
+        if !path.is_empty() {
+            condition.push_str(&format!(" {}", path));
+        }
+        Some(condition)
     } else {
-        basic_template.push_str(format!("\"__fish_seen_subcommand_from {}\"", subcommand).as_str());
-    }
+        None
+    };
+    let basic_template = template_of(root_command, condition.clone());
 
-    for option in opts!(comp_gen.0) {
-        let mut template = basic_template.clone();
-        if let Some(data) = option.short {
+    for arg in cmd.opts().chain(cmd.flags()) {
+        let mut template = template_of(root_command, guarded(condition.clone(), cmd, arg));
+        if let Some(data) = arg.short {
             template.push_str(format!(" -s {}", data).as_str());
         }
-        if let Some(data) = option.long {
+        if let Some(ref data) = arg.long {
             template.push_str(format!(" -l {}", data).as_str());
         }
-        if let Some(data) = option.help {
-            template.push_str(format!(" -d '{}'", escape_string(data)).as_str());
+        if let Some(data) = shells::help_with_heading(arg) {
+            template.push_str(format!(" -d '{}'", escape_string(&data)).as_str());
         }
-        if let Some(ref data) = option.possible_vals {
-            template.push_str(format!(" -r -f -a \"{}\"", data.join(" ")).as_str());
+        if !arg.takes_value() {
+            template.push_str(" -f");
+        } else if !arg.possible_values.is_empty() {
+            template.push_str(format!(" -x -a {}", value_list(&arg.possible_values)).as_str());
+        } else {
+            template.push_str(value_completion(arg.value_hint));
         }
         buffer.push_str(template.as_str());
         buffer.push_str("\n");
     }
 
-    for flag in flags!(comp_gen.0) {
+    // `-f` would also turn off the files for the other positional arguments of the command
+    let no_files = !cmd.positionals().any(completes_files);
+    for arg in cmd.positionals().filter(|a| !a.possible_values.is_empty()) {
         let mut template = basic_template.clone();
-        if let Some(data) = flag.short {
-            template.push_str(format!(" -s {}", data).as_str());
-        }
-        if let Some(data) = flag.long {
-            template.push_str(format!(" -l {}", data).as_str());
+        if no_files {
+            template.push_str(" -f");
         }
-        if let Some(data) = flag.help {
+        template.push_str(format!(" -a {}", value_list(&arg.possible_values)).as_str());
+        if let Some(ref data) = arg.help {
             template.push_str(format!(" -d '{}'", escape_string(data)).as_str());
         }
         buffer.push_str(template.as_str());
         buffer.push_str("\n");
     }
 
-    for subcommand in subcommands!(comp_gen.0) {
-        let mut template = basic_template.clone();
-        template.push_str(" -f");
-        template.push_str(format!(" -a \"{}\"", &subcommand.name).as_str());
-        if let Some(data) = subcommand.about {
-            template.push_str(format!(" -d '{}'", escape_string(data)).as_str())
+    for subcommand in &cmd.subcommands {
+        let names = Some(&subcommand.name).into_iter().chain(
+            subcommand
+                .aliases
+                .iter()
+                .filter(|a| a.visible)
+                .map(|a| &a.name),
+        );
+        for name in names {
+            let mut template = basic_template.clone();
+            template.push_str(" -f");
+            template.push_str(format!(" -a \"{}\"", name).as_str());
+            if let Some(ref data) = subcommand.about {
+                template.push_str(format!(" -d '{}'", escape_string(data)).as_str())
+            }
+            buffer.push_str(template.as_str());
+            buffer.push_str("\n");
         }
-        buffer.push_str(template.as_str());
-        buffer.push_str("\n");
     }
 
     // generate options of subcommands
-    for subcommand in &comp_gen.0.subcommands {
-        let sub_comp_gen = FishGen::new(&subcommand);
-        gen_fish_inner(root_command, &sub_comp_gen, &subcommand.to_string(), buffer);
+    for subcommand in &cmd.subcommands {
+        let sc_path = if path.is_empty() {
+            subcommand.name.clone()
+        } else {
+            format!("{} {}", path, subcommand.name)
+        };
+        gen_fish_inner(root_command, subcommand, &sc_path, buffer);
+    }
+}
+
+fn template_of(root_command: &str, condition: Option<String>) -> String {
+    match condition {
+        Some(condition) => format!("complete -c {} -n \"{}\"", root_command, condition),
+        None => format!("complete -c {}", root_command),
+    }
+}
+
+// Adds to `condition` that neither `arg` itself, unless it may be given more than once, nor any
+// argument it conflicts with is on the command line yet
+fn guarded(condition: Option<String>, cmd: &CommandSpec, arg: &ArgSpec) -> Option<String> {
+    // Conflicts go both ways, but are only declared on one of the args
+    let excluded: Vec<&ArgSpec> = cmd.flags()
+        .chain(cmd.opts())
+        .filter(|a| {
+            (a.name == arg.name && !arg.multiple_occurrences) || arg.conflicts.contains(&a.name)
+                || a.conflicts.contains(&arg.name)
+        })
+        .collect();
+    if excluded.is_empty() {
+        return condition;
+    }
+
+    let mut options = vec![];
+    for a in &excluded {
+        if let Some(short) = a.short {
+            options.push(format!("-s {}", short));
+        }
+    }
+    for a in &excluded {
+        options.extend(a.longs().into_iter().map(String::from));
+    }
+    let guard = format!("not __fish_contains_opt {}", options.join(" "));
+    Some(match condition {
+        Some(condition) => format!("{}; and {}", condition, guard),
+        None => guard,
+    })
+}
+
+// Gets `values` as the double quoted argument of `-a`, which fish splits like a command line
+fn value_list(values: &[String]) -> String {
+    let words: Vec<String> = values
+        .iter()
+        .map(|v| {
+            if v.chars().all(|c| c.is_ascii_alphanumeric() || "_-.,:+=@%/".contains(c)) {
+                v.clone()
+            } else {
+                format!("'{}'", escape_string(v))
+            }
+        })
+        .collect();
+    format!(
+        "\"{}\"",
+        words.join(" ").replace("\\", "\\\\").replace("\"", "\\\"").replace("$", "\\$")
+    )
+}
+
+// Whether fish should complete files for the values of the positional argument `arg`
+fn completes_files(arg: &ArgSpec) -> bool {
+    arg.possible_values.is_empty() && match arg.value_hint {
+        ValueHint::Unknown
+        | ValueHint::AnyPath
+        | ValueHint::FilePath
+        | ValueHint::DirPath
+        | ValueHint::ExecutablePath => true,
+        _ => false,
+    }
+}
+
+// Gets the flags completing values of the kind `hint`
+fn value_completion(hint: ValueHint) -> &'static str {
+    match hint {
+        ValueHint::Unknown => " -r",
+        ValueHint::AnyPath | ValueHint::FilePath | ValueHint::ExecutablePath => " -r -F",
+        ValueHint::DirPath => " -x -a \"(__fish_complete_directories)\"",
+        ValueHint::CommandName => " -x -a \"(__fish_complete_command)\"",
+        ValueHint::Username => " -x -a \"(__fish_complete_users)\"",
+        ValueHint::Hostname => " -x -a \"(__fish_print_hostnames)\"",
+        ValueHint::Url | ValueHint::Other => " -x",
     }
 }
@@ -1,57 +1,101 @@
 mod bash;
+mod carapace;
+mod clink;
 mod elvish;
+mod fig;
 mod fish;
+mod nushell;
 mod powershell;
+mod tcsh;
+mod xonsh;
 mod zsh;
 
 // Std
 use std::io::Write;
 
 // Internal
-use self::bash::BashGen;
-use self::elvish::ElvishGen;
-use self::fish::FishGen;
-use self::powershell::PowerShellGen;
-use self::zsh::ZshGen;
-use clap::App;
+use errors::{self, Error};
+pub use self::bash::BashGen;
+pub use self::carapace::CarapaceGen;
+pub use self::clink::ClinkGen;
+pub use self::elvish::ElvishGen;
+pub use self::fig::FigGen;
+pub use self::fish::FishGen;
+pub use self::nushell::NushellGen;
+pub use self::powershell::PowerShellGen;
+pub use self::tcsh::TcshGen;
+pub use self::xonsh::XonshGen;
+pub use self::zsh::ZshGen;
+use spec::{ArgSpec, CommandSpec};
 
-pub struct ComplGen<'a, 'b>(&'b App<'a, 'b>)
-where
-    'a: 'b;
+/// A generator of completion scripts (or any other output) for a command.
+///
+/// All of the built-in [`Shell`](./enum.Shell.html)s are backed by an implementation of this
+/// trait, and third party crates can implement it to plug in their own completion targets via
+/// [`generate_with`](./fn.generate_with.html).
+///
+/// # Examples
+///
+/// ```ignore
+/// use std::io::Write;
+/// use clap_generate::{CommandSpec, Generator};
+///
+/// pub struct Wrapper;
+///
+/// impl Generator for Wrapper {
+///     fn file_name(name: &str) -> String { format!("{}.wrap", name) }
+///
+///     fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<(), clap_generate::Error> {
+///         for sc in cmd.all_commands() {
+///             buf.write_all(format!("{}\n", sc.bin_name).as_bytes())?;
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait Generator {
+    /// Returns the file name the output for a binary called `name` is conventionally written to,
+    /// e.g. `myapp.bash` or `_myapp`.
+    fn file_name(name: &str) -> String;
 
-impl<'a, 'b> ComplGen<'a, 'b> {
-    pub fn new(app: &'b App<'a, 'b>) -> Self { ComplGen(app) }
+    /// Writes the output for `cmd` (the description of the root `App`) to `buf`.
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> errors::Result<()>;
+}
+
+pub struct ComplGen<'a>(&'a CommandSpec);
 
-    pub fn generate<W: Write>(&self, for_shell: Shell, buf: &mut W) {
+impl<'a> ComplGen<'a> {
+    pub fn new(cmd: &'a CommandSpec) -> Self { ComplGen(cmd) }
+
+    pub fn file_name(for_shell: Shell, name: &str) -> errors::Result<String> {
         match for_shell {
-            Shell::Bash => BashGen::new(self.0).generate_to(buf),
-            Shell::Fish => FishGen::new(self.0).generate_to(buf),
-            Shell::Zsh => ZshGen::new(self.0).generate_to(buf),
-            Shell::PowerShell => PowerShellGen::new(self.0).generate_to(buf),
-            Shell::Elvish => ElvishGen::new(self.0).generate_to(buf),
-            _ => panic!("Unsupported shell type for generating completions"),
+            Shell::Bash => Ok(BashGen::file_name(name)),
+            Shell::Fish => Ok(FishGen::file_name(name)),
+            Shell::Zsh => Ok(ZshGen::file_name(name)),
+            Shell::PowerShell => Ok(PowerShellGen::file_name(name)),
+            Shell::Elvish => Ok(ElvishGen::file_name(name)),
+            Shell::Nushell => Ok(NushellGen::file_name(name)),
+            Shell::Tcsh => Ok(TcshGen::file_name(name)),
+            Shell::Xonsh => Ok(XonshGen::file_name(name)),
+            Shell::Clink => Ok(ClinkGen::file_name(name)),
+            _ => Err(Error::UnsupportedShell),
         }
     }
-}
 
-// Gets all subcommands including child subcommands in the form of 'name' where the name
-// is a single word (i.e. "install")  of the path to said subcommand (i.e.
-// "rustup toolchain install")
-//
-// Also note, aliases are treated as their own subcommands but duplicates of whatever they're
-// aliasing.
-pub fn all_subcommand_names(p: &App) -> Vec<String> {
-    debugln!("all_subcommand_names;");
-    let mut subcmds: Vec<_> = subcommands_of(p)
-        .iter()
-        .map(|&(ref n, _)| n.clone())
-        .collect();
-    for sc_v in subcommands!(p).map(|s| all_subcommand_names(&s)) {
-        subcmds.extend(sc_v);
+    pub fn generate<W: Write>(&self, for_shell: Shell, buf: &mut W) -> errors::Result<()> {
+        match for_shell {
+            Shell::Bash => BashGen::generate(self.0, buf),
+            Shell::Fish => FishGen::generate(self.0, buf),
+            Shell::Zsh => ZshGen::generate(self.0, buf),
+            Shell::PowerShell => PowerShellGen::generate(self.0, buf),
+            Shell::Elvish => ElvishGen::generate(self.0, buf),
+            Shell::Nushell => NushellGen::generate(self.0, buf),
+            Shell::Tcsh => TcshGen::generate(self.0, buf),
+            Shell::Xonsh => XonshGen::generate(self.0, buf),
+            Shell::Clink => ClinkGen::generate(self.0, buf),
+            _ => Err(Error::UnsupportedShell),
+        }
     }
-    subcmds.sort();
-    subcmds.dedup();
-    subcmds
 }
 
 // Gets all subcommands including child subcommands in the form of ('name', 'bin_name') where the name
@@ -60,10 +104,10 @@ pub fn all_subcommand_names(p: &App) -> Vec<String> {
 //
 // Also note, aliases are treated as their own subcommands but duplicates of whatever they're
 // aliasing.
-pub fn all_subcommands(p: &App) -> Vec<(String, String)> {
+pub fn all_subcommands(p: &CommandSpec) -> Vec<(String, String)> {
     debugln!("all_subcommands;");
     let mut subcmds: Vec<_> = subcommands_of(p);
-    for sc_v in subcommands!(p).map(|s| all_subcommands(&s)) {
+    for sc_v in p.subcommands.iter().map(all_subcommands) {
         subcmds.extend(sc_v);
     }
     subcmds
@@ -75,87 +119,62 @@ pub fn all_subcommands(p: &App) -> Vec<(String, String)> {
 //
 // Also note, aliases are treated as their own subcommands but duplicates of whatever they're
 // aliasing.
-pub fn subcommands_of(p: &App) -> Vec<(String, String)> {
+pub fn subcommands_of(p: &CommandSpec) -> Vec<(String, String)> {
     debugln!(
         "subcommands_of: name={}, bin_name={}",
         p.name,
-        p.bin_name.as_ref().unwrap()
+        p.bin_name
     );
     let mut subcmds = vec![];
 
-    debugln!(
-        "subcommands_of: Has subcommands...{:?}",
-        p.has_subcommands()
-    );
-    if !p.has_subcommands() {
-        let mut ret = vec![];
-        debugln!("subcommands_of: Looking for aliases...");
-        if let Some(ref aliases) = p.aliases {
-            for &(n, _) in aliases {
-                debugln!("subcommands_of:iter:iter: Found alias...{}", n);
-                let mut als_bin_name: Vec<_> = p.bin_name.as_ref().unwrap().split(' ').collect();
-                als_bin_name.push(n);
-                let old = als_bin_name.len() - 2;
-                als_bin_name.swap_remove(old);
-                ret.push((n.to_owned(), als_bin_name.join(" ")));
-            }
-        }
-        return ret;
-    }
-    for sc in subcommands!(p) {
+    for sc in &p.subcommands {
         debugln!(
             "subcommands_of:iter: name={}, bin_name={}",
             sc.name,
-            sc.bin_name.as_ref().unwrap()
+            sc.bin_name
         );
 
         debugln!("subcommands_of:iter: Looking for aliases...");
-        if let Some(ref aliases) = sc.aliases {
-            for &(n, _) in aliases {
-                debugln!("subcommands_of:iter:iter: Found alias...{}", n);
-                let mut als_bin_name: Vec<_> = p.bin_name.as_ref().unwrap().split(' ').collect();
-                als_bin_name.push(n);
-                let old = als_bin_name.len() - 2;
-                als_bin_name.swap_remove(old);
-                subcmds.push((n.to_owned(), als_bin_name.join(" ")));
-            }
+        for alias in &sc.aliases {
+            debugln!("subcommands_of:iter:iter: Found alias...{}", alias.name);
+            subcmds.push((
+                alias.name.clone(),
+                format!("{} {}", p.bin_name, alias.name),
+            ));
         }
-        subcmds.push((sc.name.clone(), sc.bin_name.as_ref().unwrap().clone()));
+        subcmds.push((sc.name.clone(), sc.bin_name.clone()));
     }
     subcmds
 }
 
-pub fn get_all_subcommand_paths(p: &App, first: bool) -> Vec<String> {
-    debugln!("get_all_subcommand_paths;");
-    let mut subcmds = vec![];
-    if !p.has_subcommands() {
-        if !first {
-            let name = &*p.name;
-            let path = p.bin_name.as_ref().unwrap().clone().replace(" ", "__");
-            let mut ret = vec![path.clone()];
-            if let Some(ref aliases) = p.aliases {
-                for &(n, _) in aliases {
-                    ret.push(path.replace(name, n));
-                }
-            }
-            return ret;
+// Gets the custom help headings used by the args of `p`, in the order they first appear
+pub fn help_headings(p: &CommandSpec) -> Vec<&str> {
+    debugln!("help_headings;");
+    let mut headings = vec![];
+    for heading in p.args.iter().filter_map(|a| a.heading.as_ref()) {
+        if !headings.contains(&&**heading) {
+            headings.push(&**heading);
         }
-        return vec![];
     }
-    for sc in subcommands!(p) {
-        let name = &*sc.name;
-        let path = sc.bin_name.as_ref().unwrap().clone().replace(" ", "__");
-        subcmds.push(path.clone());
-        if let Some(ref aliases) = sc.aliases {
-            for &(n, _) in aliases {
-                subcmds.push(path.replace(name, n));
-            }
-        }
-    }
-    for sc_v in subcommands!(p).map(|s| get_all_subcommand_paths(&s, false)) {
-        subcmds.extend(sc_v);
+    headings
+}
+
+// Gets the help message of an arg prefixed by its custom help heading (i.e. "Networking: the port
+// to bind to"), for shells which can only display a single description per candidate
+pub fn help_with_heading(arg: &ArgSpec) -> Option<String> {
+    match (&arg.heading, &arg.help) {
+        (&Some(ref heading), &Some(ref help)) => Some(format!("{}: {}", heading, help)),
+        (&Some(ref heading), &None) => Some(heading.clone()),
+        (&None, help) => help.clone(),
     }
-    subcmds
+}
+
+// Gets a name usable as (part of) an identifier in every shell by replacing all the characters
+// which aren't ASCII alphanumerics with `_`, i.e. `my_app` for the binary `my-app`
+pub fn identifier(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
 }
 #[allow(unused_imports)]
 use std::ascii::AsciiExt;
@@ -164,7 +183,7 @@ use std::str::FromStr;
 
 /// Describes which shell to produce a completions file for
 #[cfg_attr(feature = "lints", allow(enum_variant_names))]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Shell {
     /// Generates a .bash completion file for the Bourne Again SHell (BASH)
     Bash,
@@ -176,13 +195,48 @@ pub enum Shell {
     PowerShell,
     /// Generates a completion file for Elvish
     Elvish,
+    /// Generates a completion module for Nushell
+    Nushell,
+    /// Generates a completion file for the TENEX C Shell (tcsh)
+    Tcsh,
+    /// Generates a completer module for xonsh
+    Xonsh,
+    /// Generates a Lua argmatcher script for Clink (cmd.exe)
+    Clink,
     #[doc(hidden)]
     __Nonexhaustive,
 }
 
 impl Shell {
     /// A list of possible variants in `&'static str` form
-    pub fn variants() -> [&'static str; 5] { ["zsh", "bash", "fish", "powershell", "elvish"] }
+    pub fn variants() -> [&'static str; 9] {
+        [
+            "zsh",
+            "bash",
+            "fish",
+            "powershell",
+            "elvish",
+            "nushell",
+            "tcsh",
+            "xonsh",
+            "clink",
+        ]
+    }
+
+    /// All supported shells, in the same order as [`variants`](#method.variants)
+    pub fn all() -> [Shell; 9] {
+        [
+            Shell::Zsh,
+            Shell::Bash,
+            Shell::Fish,
+            Shell::PowerShell,
+            Shell::Elvish,
+            Shell::Nushell,
+            Shell::Tcsh,
+            Shell::Xonsh,
+            Shell::Clink,
+        ]
+    }
 }
 
 impl FromStr for Shell {
@@ -195,8 +249,12 @@ impl FromStr for Shell {
             "BASH" | _ if s.eq_ignore_ascii_case("bash") => Ok(Shell::Bash),
             "POWERSHELL" | _ if s.eq_ignore_ascii_case("powershell") => Ok(Shell::PowerShell),
             "ELVISH" | _ if s.eq_ignore_ascii_case("elvish") => Ok(Shell::Elvish),
+            "NUSHELL" | _ if s.eq_ignore_ascii_case("nushell") => Ok(Shell::Nushell),
+            "TCSH" | _ if s.eq_ignore_ascii_case("tcsh") => Ok(Shell::Tcsh),
+            "XONSH" | _ if s.eq_ignore_ascii_case("xonsh") => Ok(Shell::Xonsh),
+            "CLINK" | _ if s.eq_ignore_ascii_case("clink") => Ok(Shell::Clink),
             _ => Err(String::from(
-                "[valid values: bash, fish, zsh, powershell, elvish]",
+                "[valid values: bash, fish, zsh, powershell, elvish, nushell, tcsh, xonsh, clink]",
             )),
         }
     }
@@ -210,7 +268,11 @@ impl fmt::Display for Shell {
             Shell::Zsh => write!(f, "ZSH"),
             Shell::PowerShell => write!(f, "POWERSHELL"),
             Shell::Elvish => write!(f, "ELVISH"),
-            _ => panic!("Unsupported shell type for completion generation"),
+            Shell::Nushell => write!(f, "NUSHELL"),
+            Shell::Tcsh => write!(f, "TCSH"),
+            Shell::Xonsh => write!(f, "XONSH"),
+            Shell::Clink => write!(f, "CLINK"),
+            _ => write!(f, "UNSUPPORTED"),
         }
     }
 }
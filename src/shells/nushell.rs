@@ -0,0 +1,142 @@
+// Std
+use std::io::Write;
+
+// Internal
+use errors::Result;
+use shells::{self, Generator};
+use spec::{ArgKind, ArgSpec, CommandSpec};
+
+/// Generates a completion module for Nushell
+#[derive(Debug, Copy, Clone)]
+pub struct NushellGen;
+
+impl Generator for NushellGen {
+    fn file_name(name: &str) -> String { format!("{}.nu", name) }
+
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<()> {
+        let mut result = String::from("module completions {\n");
+
+        for sc in cmd.all_commands() {
+            result.push_str(&completers_of(sc));
+        }
+
+        // Subcommand aliases get their own declaration, the paths of which are relative to the
+        // root so that they can be declared for every name of the binary
+        let mut paths = vec![(String::new(), cmd)];
+        for (_, bin_name) in shells::all_subcommands(cmd) {
+            let path: Vec<&str> = bin_name.split(' ').skip(1).collect();
+            let sc = cmd.find_subcommand_by_path(&path).expect(::INTERNAL_ERROR_MSG);
+            paths.push((path.join(" "), sc));
+        }
+        for name in cmd.bin_names() {
+            for &(ref path, sc) in &paths {
+                let command = if path.is_empty() {
+                    name.to_owned()
+                } else {
+                    format!("{} {}", name, path)
+                };
+                result.push_str(&extern_of(&command, sc));
+            }
+        }
+
+        result.push_str("}\n\nexport use completions *\n");
+
+        w!(buf, result.as_bytes());
+        Ok(())
+    }
+}
+
+// Escape string inside double quotes
+fn escape_string(string: &str) -> String { string.replace("\\", "\\\\").replace("\"", "\\\"") }
+
+// Comments end at the line break, so the description must be a single line
+fn comment(help: Option<&str>) -> String {
+    match help {
+        Some(help) => format!("  # {}", help.replace("\n", " ")),
+        None => String::new(),
+    }
+}
+
+fn completer_name(p: &CommandSpec, arg: &ArgSpec) -> String {
+    format!("nu-complete {} {}", p.bin_name, arg.name)
+}
+
+// Defines the custom completers of the args of `p` which have possible values
+fn completers_of(p: &CommandSpec) -> String {
+    debugln!("NushellGen::completers_of: name={}", p.name);
+    let mut ret = String::new();
+    for arg in p.args.iter().filter(|a| !a.possible_values.is_empty()) {
+        ret.push_str(&format!(
+            "\n  def \"{}\" [] {{\n    [ {} ]\n  }}\n",
+            escape_string(&completer_name(p, arg)),
+            arg.possible_values
+                .iter()
+                .map(|v| format!("\"{}\"", escape_string(v)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ));
+    }
+    ret
+}
+
+// Declares the signature of the command `p` which is called as `command`
+fn extern_of(command: &str, p: &CommandSpec) -> String {
+    debugln!("NushellGen::extern_of: command={}", command);
+    let mut ret = String::from("\n");
+    if let Some(ref about) = p.about {
+        ret.push_str(&format!("  # {}\n", about.replace("\n", " ")));
+    }
+    ret.push_str(&format!("  export extern \"{}\" [\n", escape_string(command)));
+
+    // An extern flag has at most one long name, so each long alias gets its own flag
+    for arg in p.args.iter().filter(|a| a.kind != ArgKind::Positional) {
+        let name = match (&arg.long, arg.short) {
+            (&Some(ref l), Some(s)) => format!("--{}(-{})", l, s),
+            (&Some(ref l), None) => format!("--{}", l),
+            (&None, Some(s)) => format!("-{}", s),
+            (&None, None) => continue,
+        };
+        let names = Some(name)
+            .into_iter()
+            .chain(arg.aliases.iter().map(|a| format!("--{}", a.name)));
+        for name in names {
+            ret.push_str(&format!(
+                "    {}{}{}\n",
+                name,
+                type_of(p, arg),
+                comment(shells::help_with_heading(arg).as_ref().map(|s| &**s))
+            ));
+        }
+    }
+
+    for arg in p.positionals() {
+        let name = shells::identifier(&arg.name);
+        let name = if arg.multiple_values || arg.multiple_occurrences {
+            format!("...{}", name)
+        } else if arg.required {
+            name
+        } else {
+            format!("{}?", name)
+        };
+        ret.push_str(&format!(
+            "    {}{}{}\n",
+            name,
+            type_of(p, arg),
+            comment(arg.help.as_ref().map(|s| &**s))
+        ));
+    }
+
+    ret.push_str("  ]\n");
+    ret
+}
+
+// Gets the type annotation of an arg of `p`, including its custom completer if any
+fn type_of(p: &CommandSpec, arg: &ArgSpec) -> String {
+    if !arg.takes_value() {
+        String::new()
+    } else if arg.possible_values.is_empty() {
+        String::from(": string")
+    } else {
+        format!(": string@\"{}\"", escape_string(&completer_name(p, arg)))
+    }
+}
@@ -2,28 +2,43 @@
 use std::io::Write;
 
 // Internal
-use clap::App;
-use INTERNAL_ERROR_MSG;
-
-pub struct PowerShellGen<'a, 'b>(&'b App<'a, 'b>)
-where
-    'a: 'b;
-
-impl<'a, 'b> PowerShellGen<'a, 'b> {
-    pub fn new(app: &'b App<'a, 'b>) -> Self { PowerShellGen(app) }
-
-    pub fn generate_to<W: Write>(&self, buf: &mut W) {
-        let bin_name = self.0.bin_name.as_ref().unwrap();
-
-        let mut names = vec![];
-        let subcommands_cases = generate_inner(self.0, "", &mut names);
+use errors::Result;
+use shells::{self, Generator};
+use spec::{CommandSpec, ValueHint};
+
+/// Generates a completion file for PowerShell
+#[derive(Debug, Copy, Clone)]
+pub struct PowerShellGen;
+
+impl Generator for PowerShellGen {
+    fn file_name(name: &str) -> String { format!("_{}.ps1", name) }
+
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<()> {
+        let bin_name = &cmd.bin_name;
+        let subcommands_cases = generate_inner(cmd, "");
+        let value_hints = generate_value_hints(cmd, "");
+        let value_hints = if value_hints.is_empty() {
+            String::new()
+        } else {
+            format!(
+                r#"
+    $previous = "$($commandElements[-1])"
+    if ($wordToComplete) {{
+        $previous = "$($commandElements[-2])"
+    }}
+    switch ("$command;$previous") {{{}
+    }}
+"#,
+                value_hints
+            )
+        };
 
         let result = format!(
             r#"
 using namespace System.Management.Automation
 using namespace System.Management.Automation.Language
 
-Register-ArgumentCompleter -Native -CommandName '{bin_name}' -ScriptBlock {{
+Register-ArgumentCompleter -Native -CommandName {command_names} -ScriptBlock {{
     param($wordToComplete, $commandAst, $cursorPosition)
 
     $commandElements = $commandAst.CommandElements
@@ -38,7 +53,7 @@ Register-ArgumentCompleter -Native -CommandName '{bin_name}' -ScriptBlock {{
         }}
         $element.Value
     }}) -join ';'
-
+{value_hints}
     $completions = @(switch ($command) {{{subcommands_cases}
     }})
 
@@ -47,10 +62,18 @@ Register-ArgumentCompleter -Native -CommandName '{bin_name}' -ScriptBlock {{
 }}
 "#,
             bin_name = bin_name,
-            subcommands_cases = subcommands_cases
+            command_names = cmd
+                .bin_names()
+                .iter()
+                .map(|n| format!("'{}'", n))
+                .collect::<Vec<_>>()
+                .join(", "),
+            subcommands_cases = subcommands_cases,
+            value_hints = value_hints
         );
 
         w!(buf, result.as_bytes());
+        Ok(())
     }
 }
 
@@ -64,14 +87,10 @@ fn get_tooltip<T: ToString>(help: Option<&str>, data: T) -> String {
     }
 }
 
-fn generate_inner<'a, 'b, 'p>(
-    p: &'p App<'a, 'b>,
-    previous_command_name: &str,
-    names: &mut Vec<&'p str>,
-) -> String {
+fn generate_inner(p: &CommandSpec, previous_command_name: &str) -> String {
     debugln!("PowerShellGen::generate_inner;");
     let command_name = if previous_command_name.is_empty() {
-        p.bin_name.as_ref().expect(INTERNAL_ERROR_MSG).clone()
+        p.bin_name.clone()
     } else {
         format!("{};{}", previous_command_name, &p.name)
     };
@@ -79,9 +98,10 @@ fn generate_inner<'a, 'b, 'p>(
     let mut completions = String::new();
     let preamble = String::from("\n            [CompletionResult]::new(");
 
-    for option in opts!(p) {
+    for option in p.opts() {
+        let help = shells::help_with_heading(option);
         if let Some(data) = option.short {
-            let tooltip = get_tooltip(option.help, data);
+            let tooltip = get_tooltip(help.as_ref().map(|h| &**h), data);
             completions.push_str(&preamble);
             completions.push_str(
                 format!(
@@ -90,8 +110,8 @@ fn generate_inner<'a, 'b, 'p>(
                 ).as_str(),
             );
         }
-        if let Some(data) = option.long {
-            let tooltip = get_tooltip(option.help, data);
+        if let Some(ref data) = option.long {
+            let tooltip = get_tooltip(help.as_ref().map(|h| &**h), data);
             completions.push_str(&preamble);
             completions.push_str(
                 format!(
@@ -102,9 +122,10 @@ fn generate_inner<'a, 'b, 'p>(
         }
     }
 
-    for flag in flags!(p) {
+    for flag in p.flags() {
+        let help = shells::help_with_heading(flag);
         if let Some(data) = flag.short {
-            let tooltip = get_tooltip(flag.help, data);
+            let tooltip = get_tooltip(help.as_ref().map(|h| &**h), data);
             completions.push_str(&preamble);
             completions.push_str(
                 format!(
@@ -113,8 +134,8 @@ fn generate_inner<'a, 'b, 'p>(
                 ).as_str(),
             );
         }
-        if let Some(data) = flag.long {
-            let tooltip = get_tooltip(flag.help, data);
+        if let Some(ref data) = flag.long {
+            let tooltip = get_tooltip(help.as_ref().map(|h| &**h), data);
             completions.push_str(&preamble);
             completions.push_str(
                 format!(
@@ -125,9 +146,9 @@ fn generate_inner<'a, 'b, 'p>(
         }
     }
 
-    for subcommand in subcommands!(p) {
+    for subcommand in &p.subcommands {
         let data = &subcommand.name;
-        let tooltip = get_tooltip(subcommand.about, data);
+        let tooltip = get_tooltip(subcommand.about.as_ref().map(|s| &**s), data);
         completions.push_str(&preamble);
         completions.push_str(
             format!(
@@ -146,9 +167,57 @@ fn generate_inner<'a, 'b, 'p>(
     );
 
     for subcommand in &p.subcommands {
-        let subcommand_subcommands_cases = generate_inner(&subcommand, &command_name, names);
+        let subcommand_subcommands_cases = generate_inner(&subcommand, &command_name);
         subcommands_cases.push_str(&subcommand_subcommands_cases);
     }
 
     subcommands_cases
 }
+
+// Generates the cases completing the values of options with a value hint, keyed by the command
+// and the option before the word being completed
+fn generate_value_hints(p: &CommandSpec, previous_command_name: &str) -> String {
+    debugln!("PowerShellGen::generate_value_hints;");
+    let command_name = if previous_command_name.is_empty() {
+        p.bin_name.clone()
+    } else {
+        format!("{};{}", previous_command_name, &p.name)
+    };
+
+    let mut cases = String::new();
+    for option in p.opts() {
+        if option.value_hint == ValueHint::Unknown || !option.possible_values.is_empty() {
+            continue;
+        }
+        // Returning nothing falls back to PowerShell's own path completion, which is only right
+        // for paths, while an empty result offers nothing
+        let action = match option.value_hint {
+            ValueHint::DirPath => {
+                "return Get-ChildItem -Directory -Name \"$wordToComplete*\" | ForEach-Object { \
+                 [CompletionResult]::new($_, $_, [CompletionResultType]::ProviderContainer, $_) }"
+            }
+            ValueHint::CommandName => {
+                "return Get-Command -CommandType Application \"$wordToComplete*\" | ForEach-Object { \
+                 [CompletionResult]::new($_.Name, $_.Name, [CompletionResultType]::Command, $_.Name) }"
+            }
+            ValueHint::Unknown
+            | ValueHint::AnyPath
+            | ValueHint::FilePath
+            | ValueHint::ExecutablePath => "return",
+            ValueHint::Username | ValueHint::Hostname | ValueHint::Url | ValueHint::Other => {
+                "return @()"
+            }
+        };
+        if let Some(short) = option.short {
+            cases.push_str(&format!("\n        '{};-{}' {{ {} }}", command_name, short, action));
+        }
+        if let Some(ref long) = option.long {
+            cases.push_str(&format!("\n        '{};--{}' {{ {} }}", command_name, long, action));
+        }
+    }
+
+    for subcommand in &p.subcommands {
+        cases.push_str(&generate_value_hints(subcommand, &command_name));
+    }
+    cases
+}
@@ -0,0 +1,172 @@
+// Std
+use std::io::Write;
+
+// Internal
+use errors::Result;
+use shells::{self, Generator};
+use spec::{ArgSpec, CommandSpec, ValueHint};
+
+/// Generates a completion file for the TENEX C Shell (tcsh)
+#[derive(Debug, Copy, Clone)]
+pub struct TcshGen;
+
+impl Generator for TcshGen {
+    fn file_name(name: &str) -> String { format!("{}.tcsh", name) }
+
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<()> {
+        let rules = rules_of(cmd).join(" \\\n    ");
+
+        let mut result = String::new();
+        for name in cmd.bin_names() {
+            result.push_str(&format!("complete {} \\\n    {}\n", name, rules));
+        }
+
+        w!(buf, result.as_bytes());
+        Ok(())
+    }
+}
+
+// Escape string inside single quotes
+fn escape_string(string: &str) -> String { string.replace("'", "'\\''") }
+
+// Gets the complete rules in the order tcsh tries them, i.e. option values before the words
+// following a command
+//
+// tcsh only looks at the words right before the one being completed, so the switches and
+// subcommands of a subcommand are offered right after its name, while any further switches are
+// those of the root command
+fn rules_of(p: &CommandSpec) -> Vec<String> {
+    debugln!("TcshGen::rules_of;");
+    // A switch shared by several commands completes the values of all of them, or nothing if
+    // they can't be combined (i.e. possible values in one command and files in another)
+    let mut values: Vec<(String, Option<Words>)> = vec![];
+    for sc in p.all_commands() {
+        for opt in sc.opts() {
+            let words = values_of(opt);
+            let switches = opt.longs()
+                .into_iter()
+                .map(|l| format!("--{}", l))
+                .chain(opt.short.map(|s| format!("-{}", s)));
+            for switch in switches {
+                match values.iter().position(|&(ref s, _)| *s == switch) {
+                    Some(i) => {
+                        let merged = values[i].1.take().and_then(|w| w.merge(&words));
+                        values[i].1 = merged;
+                    }
+                    None => values.push((switch, Some(words.clone()))),
+                }
+            }
+        }
+    }
+
+    let mut rules: Vec<String> = values
+        .into_iter()
+        .filter_map(|(switch, words)| words.map(|w| w.rule(&switch)))
+        .collect();
+    // tcsh only uses the first rule for a name, i.e. the one of the `help` closest to the root
+    let mut names: Vec<String> = vec![];
+    for (name, bin_name) in shells::all_subcommands(p) {
+        if names.contains(&name) {
+            continue;
+        }
+        let sc = p.find_subcommand_by_path(bin_name.split(' ').skip(1))
+            .expect(::INTERNAL_ERROR_MSG);
+        let words = words_after(sc);
+        if !words.is_empty() {
+            rules.push(format!("n/{}/({})/", name, words.join(" ")));
+        }
+        names.push(name);
+    }
+    let words = words_after(p);
+    if !words.is_empty() {
+        rules.push(format!("p/1/({})/", words.join(" ")));
+    }
+
+    let longs: Vec<&str> = p.flags().chain(p.opts()).flat_map(|a| a.longs()).collect();
+    if !longs.is_empty() {
+        rules.push(format!("c/--/({})/", longs.join(" ")));
+    }
+    let shorts: Vec<String> = p.flags()
+        .chain(p.opts())
+        .filter_map(|a| a.short.map(|s| s.to_string()))
+        .collect();
+    if !shorts.is_empty() {
+        rules.push(format!("c/-/({} -)/", shorts.join(" ")));
+    }
+    rules.iter().map(|r| format!("'{}'", escape_string(r))).collect()
+}
+
+// The word list completing the values of an option
+#[derive(Clone, PartialEq)]
+enum Words {
+    // The possible values
+    List(Vec<String>),
+    // One of tcsh's own lists, i.e. `f` for files
+    Builtin(&'static str),
+}
+
+impl Words {
+    // Combines the words of two options with the same switch, if they can be
+    fn merge(self, other: &Words) -> Option<Words> {
+        match (self, other) {
+            (Words::List(mut values), &Words::List(ref more)) => {
+                for v in more {
+                    if !values.contains(v) {
+                        values.push(v.clone());
+                    }
+                }
+                Some(Words::List(values))
+            }
+            (words, other) => if words == *other { Some(words) } else { None },
+        }
+    }
+
+    // Gets the `n` rule completing the words after `switch`, separated by a character which
+    // doesn't occur in them
+    fn rule(&self, switch: &str) -> String {
+        let words = match *self {
+            Words::List(ref values) => format!("({})", values.join(" ")),
+            Words::Builtin(list) => list.to_owned(),
+        };
+        let sep = "/|,:@%+"
+            .chars()
+            .find(|&c| !words.contains(c) && !switch.contains(c))
+            .unwrap_or('/');
+        format!("n{sep}{}{sep}{}{sep}", switch, words, sep = sep)
+    }
+}
+
+// Gets the word list completing the values of `opt`, where possible values containing spaces are
+// left out because tcsh can't tell them apart from two separate words
+fn values_of(opt: &ArgSpec) -> Words {
+    if !opt.possible_values.is_empty() {
+        return Words::List(
+            opt.possible_values
+                .iter()
+                .filter(|v| !v.chars().any(char::is_whitespace))
+                .cloned()
+                .collect(),
+        );
+    }
+    Words::Builtin(match opt.value_hint {
+        ValueHint::Unknown
+        | ValueHint::AnyPath
+        | ValueHint::FilePath
+        | ValueHint::ExecutablePath => "f",
+        ValueHint::DirPath => "d",
+        ValueHint::CommandName => "c",
+        ValueHint::Username => "u",
+        ValueHint::Hostname => "$hosts",
+        ValueHint::Url | ValueHint::Other => "n",
+    })
+}
+
+// Gets the words which may follow the name of `p`, i.e. its subcommands and switches
+fn words_after(p: &CommandSpec) -> Vec<String> {
+    let mut words: Vec<String> = p.subcommand_names().into_iter().map(String::from).collect();
+    for arg in p.flags().chain(p.opts()) {
+        words.extend(arg.longs().into_iter().map(|l| format!("--{}", l)));
+        words.extend(arg.short.map(|s| format!("-{}", s)));
+    }
+    words
+}
@@ -0,0 +1,173 @@
+// Std
+use std::io::Write;
+
+// Internal
+use errors::Result;
+use shells::{self, Generator};
+use spec::CommandSpec;
+use INTERNAL_ERROR_MSG;
+
+/// Generates a completer module for xonsh
+#[derive(Debug, Copy, Clone)]
+pub struct XonshGen;
+
+impl Generator for XonshGen {
+    fn file_name(name: &str) -> String { format!("{}.xsh", name) }
+
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<()> {
+        let bin_name = &cmd.bin_name;
+        let ident = shells::identifier(bin_name);
+        let bin_names = cmd.bin_names();
+
+        let mut result = format!(
+            r#"from xonsh.completers.tools import RichCompletion
+
+
+_{upper}_COMPLETIONS = {{{subcommands_cases}
+}}
+
+_{upper}_VALUES = {{{values_cases}
+}}
+
+
+def _{ident}_completer(prefix, line, begidx, endidx, ctx):
+    words = line[:begidx].split()
+    if not words or words[0] not in ({names},):
+        return None
+    command = '{bin_name}'
+    for word in words[1:]:
+        if word.startswith('-'):
+            break
+        command += ';' + word
+    key = command + ';' + words[-1]
+    if key in _{upper}_VALUES:
+        candidates = _{upper}_VALUES[key]
+    else:
+        candidates = _{upper}_COMPLETIONS.get(command, [])
+    completions = {{
+        RichCompletion(value, description=desc)
+        for value, desc in candidates
+        if value.startswith(prefix)
+    }}
+    return completions or None
+
+
+"#,
+            upper = ident.to_uppercase(),
+            ident = ident,
+            bin_name = escape_string(bin_name),
+            names = bin_names
+                .iter()
+                .map(|n| format!("'{}'", escape_string(n)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            subcommands_cases = generate_inner(cmd, bin_name),
+            values_cases = generate_values(cmd, bin_name)
+        );
+        for name in bin_names {
+            result.push_str(&format!(
+                "completer add {} _{}_completer \"start\"\n",
+                name, ident
+            ));
+        }
+
+        w!(buf, result.as_bytes());
+        Ok(())
+    }
+}
+
+// Escape string inside single quotes
+fn escape_string(string: &str) -> String { string.replace("\\", "\\\\").replace("'", "\\'") }
+
+fn get_tooltip<T: ToString>(help: Option<&str>, data: T) -> String {
+    match help {
+        Some(help) => escape_string(&help.replace("\n", " ")),
+        _ => escape_string(&data.to_string()),
+    }
+}
+
+// Gets the entries of `p` and all of its subcommands, keyed by the path which reaches them, i.e.
+// `myapp;remote;add`, with an entry for every alias along the way
+fn generate_inner(p: &CommandSpec, command_name: &str) -> String {
+    debugln!("XonshGen::generate_inner: command_name={}", command_name);
+
+    let mut completions = String::new();
+    let preamble = String::from("\n        (");
+
+    for arg in p.opts().chain(p.flags()) {
+        let help = shells::help_with_heading(arg);
+        if let Some(data) = arg.short {
+            let tooltip = get_tooltip(help.as_ref().map(|h| &**h), data);
+            completions.push_str(&preamble);
+            completions.push_str(format!("'-{}', '{}'),", data, tooltip).as_str());
+        }
+        if let Some(ref data) = arg.long {
+            let tooltip = get_tooltip(help.as_ref().map(|h| &**h), data);
+            completions.push_str(&preamble);
+            completions.push_str(format!("'--{}', '{}'),", data, tooltip).as_str());
+        }
+    }
+
+    for subcommand in &p.subcommands {
+        let data = &subcommand.name;
+        let tooltip = get_tooltip(subcommand.about.as_ref().map(|s| &**s), data);
+        completions.push_str(&preamble);
+        completions.push_str(format!("'{}', '{}'),", escape_string(data), tooltip).as_str());
+    }
+
+    let mut subcommands_cases = format!(
+        r"
+    '{}': [{}
+    ],",
+        escape_string(command_name), completions
+    );
+
+    for (name, _) in shells::subcommands_of(p) {
+        let subcommand = p.find_subcommand(&name).expect(INTERNAL_ERROR_MSG);
+        let subcommand_subcommands_cases =
+            generate_inner(subcommand, &format!("{};{}", command_name, name));
+        subcommands_cases.push_str(&subcommand_subcommands_cases);
+    }
+
+    subcommands_cases
+}
+
+// Gets the possible values of the options, keyed by the command and the option before the word
+// being completed
+fn generate_values(p: &CommandSpec, command_name: &str) -> String {
+    debugln!("XonshGen::generate_values: command_name={}", command_name);
+
+    let mut cases = String::new();
+    for option in p.opts().filter(|o| !o.possible_values.is_empty()) {
+        let values = option
+            .possible_values
+            .iter()
+            .map(|v| {
+                format!(
+                    "\n        ('{}', '{}'),",
+                    escape_string(v),
+                    get_tooltip(option.help.as_ref().map(|h| &**h), "")
+                )
+            })
+            .collect::<String>();
+        let switches = option
+            .short
+            .map(|s| format!("-{}", s))
+            .into_iter()
+            .chain(option.long.as_ref().map(|l| format!("--{}", l)));
+        for switch in switches {
+            cases.push_str(&format!(
+                "\n    '{};{}': [{}\n    ],",
+                escape_string(command_name),
+                switch,
+                values
+            ));
+        }
+    }
+
+    for (name, _) in shells::subcommands_of(p) {
+        let subcommand = p.find_subcommand(&name).expect(INTERNAL_ERROR_MSG);
+        cases.push_str(&generate_values(subcommand, &format!("{};{}", command_name, name)));
+    }
+    cases
+}
@@ -3,30 +3,26 @@
 use std::ascii::AsciiExt;
 use std::io::Write;
 
-// Third Party
-use clap::{App, ArgSettings};
-
 // Internal
-use shells;
+use errors::Result;
+use shells::{self, Generator};
+use spec::{ArgSpec, CommandSpec, ValueHint};
 use INTERNAL_ERROR_MSG;
 
-pub struct ZshGen<'a, 'b>(&'b App<'a, 'b>)
-where
-    'a: 'b;
+/// Generates a completion file for the Z SHell (ZSH)
+#[derive(Debug, Copy, Clone)]
+pub struct ZshGen;
 
-impl<'a, 'b> ZshGen<'a, 'b> {
-    pub fn new(app: &'b App<'a, 'b>) -> Self {
-        debugln!("ZshGen::new;");
-        ZshGen(app)
-    }
+impl Generator for ZshGen {
+    fn file_name(name: &str) -> String { format!("_{}", name) }
 
-    pub fn generate_to<W: Write>(&self, buf: &mut W) {
-        debugln!("ZshGen::generate_to;");
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<()> {
+        debugln!("ZshGen::generate;");
         w!(
             buf,
             format!(
                 "\
-#compdef {name}
+#compdef {bin_names}
 
 autoload -U is-at-least
 
@@ -49,12 +45,14 @@ _{name}() {{
 {subcommand_details}
 
 _{name} \"$@\"",
-                name = self.0.bin_name.as_ref().unwrap(),
-                initial_args = get_args_of(self.0),
-                subcommands = get_subcommands_of(self.0),
-                subcommand_details = subcommand_details(self.0)
+                name = cmd.bin_name,
+                bin_names = cmd.bin_names().join(" "),
+                initial_args = get_args_of(cmd),
+                subcommands = get_subcommands_of(cmd),
+                subcommand_details = subcommand_details(cmd)
             ).as_bytes()
         );
+        Ok(())
     }
 }
 
@@ -85,7 +83,7 @@ _{name} \"$@\"",
 // 	)
 // 	_describe -t commands 'rustup commands' commands "$@"
 //
-fn subcommand_details(p: &App) -> String {
+fn subcommand_details(p: &CommandSpec) -> String {
     debugln!("ZshGen::subcommand_details;");
     // First we do ourself
     let mut ret = vec![format!(
@@ -97,8 +95,8 @@ _{bin_name_underscore}_commands() {{
     )
     _describe -t commands '{bin_name} commands' commands \"$@\"
 }}",
-        bin_name_underscore = p.bin_name.as_ref().unwrap().replace(" ", "__"),
-        bin_name = p.bin_name.as_ref().unwrap(),
+        bin_name_underscore = p.bin_name.replace(" ", "__"),
+        bin_name = p.bin_name,
         subcommands_and_args = subcommands_of(p)
     )];
 
@@ -137,17 +135,18 @@ _{bin_name_underscore}_commands() {{
 // A snippet from rustup:
 // 		'show:Show the active and installed toolchains'
 //      'update:Update Rust toolchains'
-fn subcommands_of(p: &App) -> String {
+fn subcommands_of(p: &CommandSpec) -> String {
     debugln!("ZshGen::subcommands_of;");
     let mut ret = vec![];
-    fn add_sc(sc: &App, n: &str, ret: &mut Vec<String>) {
+    fn add_sc(sc: &CommandSpec, n: &str, ret: &mut Vec<String>) {
         debugln!("ZshGen::add_sc;");
         let s = format!(
             "\"{name}:{help}\" \\",
             name = n,
             help = sc
                 .about
-                .unwrap_or("")
+                .as_ref()
+                .map_or("", |s| &**s)
                 .replace("[", "\\[")
                 .replace("]", "\\]")
         );
@@ -157,13 +156,11 @@ fn subcommands_of(p: &App) -> String {
     }
 
     // The subcommands
-    for sc in subcommands!(p) {
+    for sc in &p.subcommands {
         debugln!("ZshGen::subcommands_of:iter: subcommand={}", sc.name);
         add_sc(sc, &sc.name, &mut ret);
-        if let Some(ref v) = sc.aliases {
-            for alias in v.iter().filter(|&&(_, vis)| vis).map(|&(n, _)| n) {
-                add_sc(sc, alias, &mut ret);
-            }
+        for alias in sc.aliases.iter().filter(|a| a.visible) {
+            add_sc(sc, &alias.name, &mut ret);
         }
     }
 
@@ -199,7 +196,7 @@ fn subcommands_of(p: &App) -> String {
 //    [name_hyphen] = The full space deliniated bin_name, but replace spaces with hyphens
 //    [repeat] = From the same recursive calls, but for all subcommands
 //    [subcommand_args] = The same as zsh::get_args_of
-fn get_subcommands_of(p: &App) -> String {
+fn get_subcommands_of(p: &CommandSpec) -> String {
     debugln!("get_subcommands_of;");
 
     debugln!(
@@ -239,19 +236,20 @@ fn get_subcommands_of(p: &App) -> String {
     ;;
 esac",
         name = p.name,
-        name_hyphen = p.bin_name.as_ref().unwrap().replace(" ", "-"),
+        name_hyphen = p.bin_name.replace(" ", "-"),
         subcommands = subcmds.join("\n"),
-        pos = positionals!(p).count() + 1
+        pos = p.positionals().count() + 1
     )
 }
 
-fn parser_of<'a, 'b>(p: &'b App<'a, 'b>, mut sc: &str) -> &'b App<'a, 'b> {
+fn parser_of<'a>(p: &'a CommandSpec, sc: &str) -> &'a CommandSpec {
     debugln!("parser_of: sc={}", sc);
-    if sc == p.bin_name.as_ref().unwrap_or(&String::new()) {
+    if sc == p.bin_name {
         return p;
     }
-    sc = sc.split(" ").last().unwrap();
-    find_subcmd!(p, sc).expect(INTERNAL_ERROR_MSG)
+    let depth = p.bin_name.split(' ').count();
+    p.find_subcommand_by_path(sc.split(' ').skip(depth))
+        .expect(INTERNAL_ERROR_MSG)
 }
 
 // Writes out the args section, which ends up being the flags, opts and postionals, and a jump to
@@ -274,16 +272,32 @@ fn parser_of<'a, 'b>(p: &'b App<'a, 'b>, mut sc: &str) -> &'b App<'a, 'b> {
 //    -C: modify the $context internal variable
 //    -s: Allow stacking of short args (i.e. -a -b -c => -abc)
 //    -S: Do not complete anything after '--' and treat those as argument values
-fn get_args_of(p: &App) -> String {
+//
+// Options and flags with a custom help heading come last, each heading in its own group. Groups
+// need zsh 5.4, so older versions get the same specs without the group lines:
+//
+// 	if is-at-least 5.4; then
+// 	_arguments -C -s -S \
+// 		# ... snip for brevity
+// 		+ 'Networking' \
+// 		'--port=[the port to bind to]' \
+// 	&& ret=0
+// 	else
+// 	_arguments -C -s -S \
+// 		# ... snip for brevity
+// 		'--port=[the port to bind to]' \
+// 	&& ret=0
+// 	fi
+fn get_args_of(p: &CommandSpec) -> String {
     debugln!("get_args_of;");
     let mut ret = vec![String::from("_arguments \"${_arguments_options[@]}\" \\")];
-    let opts = write_opts_of(p);
-    let flags = write_flags_of(p);
+    let opts = write_opts_of(p, None);
+    let flags = write_flags_of(p, None);
     let positionals = write_positionals_of(p);
     let sc_or_a = if p.has_subcommands() {
         format!(
             "\":: :_{name}_commands\" \\",
-            name = p.bin_name.as_ref().unwrap().replace(" ", "__")
+            name = p.bin_name.replace(" ", "__")
         )
     } else {
         String::new()
@@ -309,9 +323,31 @@ fn get_args_of(p: &App) -> String {
     if !sc.is_empty() {
         ret.push(sc);
     }
-    ret.push(String::from("&& ret=0"));
 
-    ret.join("\n")
+    let headings = shells::help_headings(p);
+    if headings.is_empty() {
+        ret.push(String::from("&& ret=0"));
+        return ret.join("\n");
+    }
+    let mut grouped = ret.clone();
+    for heading in headings {
+        grouped.push(format!("+ '{}' \\", heading.replace("'", "'\\''")));
+        let opts = write_opts_of(p, Some(heading));
+        let flags = write_flags_of(p, Some(heading));
+        if !opts.is_empty() {
+            grouped.push(opts.clone());
+            ret.push(opts);
+        }
+        if !flags.is_empty() {
+            grouped.push(flags.clone());
+            ret.push(flags);
+        }
+    }
+    format!(
+        "if is-at-least 5.4; then\n{grouped}\n&& ret=0\nelse\n{flat}\n&& ret=0\nfi",
+        grouped = grouped.join("\n"),
+        flat = ret.join("\n")
+    )
 }
 
 // Escape help string inside single quotes and brackets
@@ -333,13 +369,13 @@ fn escape_value(string: &str) -> String {
         .replace(" ", "\\ ")
 }
 
-fn write_opts_of(p: &App) -> String {
+fn write_opts_of(p: &CommandSpec, heading: Option<&str>) -> String {
     debugln!("write_opts_of;");
     let mut ret = vec![];
-    for o in opts!(p) {
+    for o in p.opts().filter(|o| o.heading.as_ref().map(|h| &**h) == heading) {
         debugln!("write_opts_of:iter: o={}", o.name);
-        let help = o.help.map_or(String::new(), escape_help);
-        let mut conflicts = get_zsh_arg_conflicts!(p, o, INTERNAL_ERROR_MSG);
+        let help = o.help.as_ref().map_or(String::new(), |h| escape_help(h));
+        let mut conflicts = get_zsh_arg_conflicts(p, o);
         conflicts = if conflicts.is_empty() {
             String::new()
         } else {
@@ -348,22 +384,22 @@ fn write_opts_of(p: &App) -> String {
 
         // @TODO @soundness should probably be either multiple occurrences or multiple values and
         // not both
-        let multiple = if o.is_set(ArgSettings::MultipleOccurrences)
-            || o.is_set(ArgSettings::MultipleValues)
-        {
+        let multiple = if o.multiple_occurrences || o.multiple_values {
             "*"
         } else {
             ""
         };
-        let pv = if let Some(ref pv_vec) = o.possible_vals {
+        let pv = if !o.possible_values.is_empty() {
             format!(
                 ": :({})",
-                pv_vec
+                o.possible_values
                     .iter()
-                    .map(|v| escape_value(*v))
+                    .map(|v| escape_value(v))
                     .collect::<Vec<String>>()
                     .join(" ")
             )
+        } else if o.value_hint != ValueHint::Unknown {
+            format!(": :{}", value_completion(o.value_hint))
         } else {
             String::new()
         };
@@ -380,7 +416,7 @@ fn write_opts_of(p: &App) -> String {
             debugln!("write_opts_of:iter: Wrote...{}", &*s);
             ret.push(s);
         }
-        if let Some(long) = o.long {
+        if let Some(ref long) = o.long {
             let l = format!(
                 "'{conflicts}{multiple}--{arg}=[{help}]{possible_values}' \\",
                 conflicts = conflicts,
@@ -398,20 +434,20 @@ fn write_opts_of(p: &App) -> String {
     ret.join("\n")
 }
 
-fn write_flags_of(p: &App) -> String {
+fn write_flags_of(p: &CommandSpec, heading: Option<&str>) -> String {
     debugln!("write_flags_of;");
     let mut ret = vec![];
-    for f in flags!(p) {
+    for f in p.flags().filter(|f| f.heading.as_ref().map(|h| &**h) == heading) {
         debugln!("write_flags_of:iter: f={}", f.name);
-        let help = f.help.map_or(String::new(), escape_help);
-        let mut conflicts = get_zsh_arg_conflicts!(p, f, INTERNAL_ERROR_MSG);
+        let help = f.help.as_ref().map_or(String::new(), |h| escape_help(h));
+        let mut conflicts = get_zsh_arg_conflicts(p, f);
         conflicts = if conflicts.is_empty() {
             String::new()
         } else {
             format!("({})", conflicts)
         };
 
-        let multiple = if f.is_set(ArgSettings::MultipleOccurrences) {
+        let multiple = if f.multiple_occurrences {
             "*"
         } else {
             ""
@@ -429,7 +465,7 @@ fn write_flags_of(p: &App) -> String {
             ret.push(s);
         }
 
-        if let Some(long) = f.long {
+        if let Some(ref long) = f.long {
             let l = format!(
                 "'{conflicts}{multiple}--{arg}[{help}]' \\",
                 conflicts = conflicts,
@@ -446,14 +482,14 @@ fn write_flags_of(p: &App) -> String {
     ret.join("\n")
 }
 
-fn write_positionals_of(p: &App) -> String {
+fn write_positionals_of(p: &CommandSpec) -> String {
     debugln!("write_positionals_of;");
     let mut ret = vec![];
-    for arg in positionals!(p) {
+    for arg in p.positionals() {
         debugln!("write_positionals_of:iter: arg={}", arg.name);
         let a = format!(
             "'{optional}:{name}{help}:{action}' \\",
-            optional = if !arg.is_set(ArgSettings::Required) {
+            optional = if !arg.required {
                 ":"
             } else {
                 ""
@@ -461,23 +497,23 @@ fn write_positionals_of(p: &App) -> String {
             name = arg.name,
             help = arg
                 .help
+                .as_ref()
                 .map_or("".to_owned(), |v| " -- ".to_owned() + v)
                 .replace("[", "\\[")
                 .replace("]", "\\]")
                 .replace(":", "\\:"),
-            action = arg
-                .possible_vals
-                .as_ref()
-                .map_or("_files".to_owned(), |values| {
-                    format!(
-                        "({})",
-                        values
-                            .iter()
-                            .map(|v| escape_value(*v))
-                            .collect::<Vec<String>>()
-                            .join(" ")
-                    )
-                })
+            action = if arg.possible_values.is_empty() {
+                value_completion(arg.value_hint).to_owned()
+            } else {
+                format!(
+                    "({})",
+                    arg.possible_values
+                        .iter()
+                        .map(|v| escape_value(v))
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                )
+            }
         );
 
         debugln!("write_positionals_of:iter: Wrote...{}", a);
@@ -486,3 +522,31 @@ fn write_positionals_of(p: &App) -> String {
 
     ret.join("\n")
 }
+
+// Gets the switches of all args `arg` conflicts with, in the form of "-v --verbose"
+fn get_zsh_arg_conflicts(p: &CommandSpec, arg: &ArgSpec) -> String {
+    let mut v = vec![];
+    for conflict in arg.conflicts.iter().filter_map(|name| p.find_arg(name)) {
+        if let Some(s) = conflict.short {
+            v.push(format!("-{}", s));
+        }
+        if let Some(ref l) = conflict.long {
+            v.push(format!("--{}", l));
+        }
+    }
+    v.join(" ")
+}
+
+// Gets the zsh action completing values of the kind `hint`
+fn value_completion(hint: ValueHint) -> &'static str {
+    match hint {
+        ValueHint::Unknown | ValueHint::AnyPath | ValueHint::FilePath => "_files",
+        ValueHint::DirPath => "_path_files -/",
+        ValueHint::ExecutablePath => "_files -g \"*(-*)\"",
+        ValueHint::CommandName => "_command_names -e",
+        ValueHint::Username => "_users",
+        ValueHint::Hostname => "_hosts",
+        ValueHint::Url => "_urls",
+        ValueHint::Other => "( )",
+    }
+}
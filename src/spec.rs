@@ -0,0 +1,672 @@
+// Std
+use std::io::{Read, Write};
+
+// Third Party
+use serde_json::{self, Value};
+#[cfg(feature = "yaml")]
+use serde_yaml;
+
+// Internal
+use clap::{App, AppSettings, Arg, ArgSettings};
+use errors::{Error, Result};
+use INTERNAL_ERROR_MSG;
+
+/// The version of the JSON schema written by [`CommandSpec::to_json`] and understood by
+/// [`CommandSpec::from_json`]. It's only bumped for changes which older readers can't cope with,
+/// new optional fields don't count.
+///
+/// [`CommandSpec::to_json`]: ./struct.CommandSpec.html#method.to_json
+/// [`CommandSpec::from_json`]: ./struct.CommandSpec.html#method.from_json
+pub const SPEC_VERSION: u64 = 1;
+
+/// A shell independent description of a command (the root `App` or any of its subcommands), built
+/// once from a `clap::App` and consumed by all of the generators and
+/// [`gen_manuals`](./fn.gen_manuals.html).
+///
+/// Unlike `clap::App` all of the fields are owned and already resolved (i.e. every subcommand
+/// knows its full `bin_name`), which makes this a convenient base for custom tooling as well.
+///
+/// # Examples
+///
+/// ```ignore
+/// let spec = CommandSpec::from_app(&build_cli());
+/// for sc in spec.all_commands() {
+///     println!("{}: {}", sc.bin_name, sc.about.as_ref().map_or("", |s| &**s));
+/// }
+/// ```
+///
+/// # JSON
+///
+/// The description can be exported with [`to_json`](#method.to_json) and loaded back with
+/// [`from_json`](#method.from_json), so tools which aren't written in Rust can get completions
+/// from the same generators. Only `name` is required for commands and `name` and `kind`
+/// (`"flag"`, `"option"` or `"positional"`) for arguments, everything else has an empty default.
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "command": {
+///     "name": "deploy",
+///     "args": [{ "name": "env", "kind": "option", "long": "env", "possible_values": ["prod", "dev"] }],
+///     "subcommands": [{ "name": "rollback", "about": "Undo the last deploy" }]
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandSpec {
+    /// The name of the command, i.e. `add` for `myapp remote add`
+    pub name: String,
+    /// The full space delimited path to the command, i.e. `myapp remote add`
+    #[serde(default)]
+    pub bin_name: String,
+    /// Additional names the root command is installed under (i.e. the symlinks of a multi-call
+    /// binary), which the completions are registered for as well
+    #[serde(default)]
+    pub bin_aliases: Vec<String>,
+    /// The short description shown in listings and `-h`
+    #[serde(default)]
+    pub about: Option<String>,
+    /// The long description shown in `--help`
+    #[serde(default)]
+    pub long_about: Option<String>,
+    /// The author(s), one per line
+    #[serde(default)]
+    pub author: Option<String>,
+    /// The version string
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Text shown before the help message
+    #[serde(default)]
+    pub before_help: Option<String>,
+    /// Text shown after the help message
+    #[serde(default)]
+    pub after_help: Option<String>,
+    /// Alternative names the command can be invoked by
+    #[serde(default)]
+    pub aliases: Vec<Alias>,
+    /// Whether the command is hidden from the help message
+    #[serde(default)]
+    pub hidden: bool,
+    /// All flags, options and positional arguments, in the order they were defined
+    #[serde(default)]
+    pub args: Vec<ArgSpec>,
+    /// The direct subcommands
+    #[serde(default)]
+    pub subcommands: Vec<CommandSpec>,
+}
+
+/// An alternative name of a command or argument
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alias {
+    /// The alias itself
+    pub name: String,
+    /// Whether the alias is shown in the help message
+    #[serde(default)]
+    pub visible: bool,
+}
+
+/// What kind of argument an [`ArgSpec`](./struct.ArgSpec.html) describes
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArgKind {
+    /// A switch which doesn't take a value (i.e. `-v` or `--verbose`)
+    Flag,
+    /// A switch which takes one or more values (i.e. `--config <FILE>`)
+    Option,
+    /// A value which is identified by its position (i.e. `<FILE>`)
+    Positional,
+}
+
+/// What kind of values an argument takes, which the generators map to the native completions of
+/// each shell (i.e. `compgen -d` or `_path_files -/` for directories)
+///
+/// Unless set explicitly (see [`CommandSpec::set_value_hint`]) the hint is inferred from the
+/// value name of the argument, i.e. `DIR` or `HOST`.
+///
+/// [`CommandSpec::set_value_hint`]: ./struct.CommandSpec.html#method.set_value_hint
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueHint {
+    /// Nothing is known about the values, the shell's default completion is used
+    Unknown,
+    /// None of the other hints apply, so no completions are offered
+    Other,
+    /// Any existing path
+    AnyPath,
+    /// The path of a file
+    FilePath,
+    /// The path of a directory
+    DirPath,
+    /// The path of an executable file
+    ExecutablePath,
+    /// The name of a command on the `PATH`
+    CommandName,
+    /// The name of a local user
+    Username,
+    /// The name of a known host
+    Hostname,
+    /// A URL
+    Url,
+}
+
+impl Default for ValueHint {
+    fn default() -> Self { ValueHint::Unknown }
+}
+
+impl ValueHint {
+    /// Infers the hint from the name of a value, i.e. `FILE`, `out_dir` or `HOSTNAME`
+    pub fn from_value_name(name: &str) -> Self {
+        let name = name.to_uppercase().replace("-", "_");
+        let last = name.rsplit('_').next().unwrap_or("");
+        match last {
+            "PATH" => ValueHint::AnyPath,
+            "FILE" | "FILENAME" => ValueHint::FilePath,
+            "DIR" | "DIRECTORY" | "FOLDER" => ValueHint::DirPath,
+            "EXE" | "EXECUTABLE" | "BINARY" | "PROGRAM" => ValueHint::ExecutablePath,
+            "CMD" | "COMMAND" => ValueHint::CommandName,
+            "USER" | "USERNAME" => ValueHint::Username,
+            "HOST" | "HOSTNAME" => ValueHint::Hostname,
+            "URL" | "URI" => ValueHint::Url,
+            _ => ValueHint::Unknown,
+        }
+    }
+}
+
+/// A shell independent description of a single argument
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArgSpec {
+    /// The name the argument is referred to by, i.e. in conflicts
+    pub name: String,
+    /// Whether this is a flag, an option or a positional argument
+    pub kind: ArgKind,
+    /// The short switch without the leading `-`
+    #[serde(default)]
+    pub short: Option<char>,
+    /// The long switch without the leading `--`
+    #[serde(default)]
+    pub long: Option<String>,
+    /// Alternative long switches
+    #[serde(default)]
+    pub aliases: Vec<Alias>,
+    /// The short help message
+    #[serde(default)]
+    pub help: Option<String>,
+    /// The long help message
+    #[serde(default)]
+    pub long_help: Option<String>,
+    /// The custom help heading the argument is listed under
+    #[serde(default)]
+    pub heading: Option<String>,
+    /// The names of the values shown in usage strings (i.e. `FILE`)
+    #[serde(default)]
+    pub value_names: Vec<String>,
+    /// The values the argument accepts, if they are restricted
+    #[serde(default)]
+    pub possible_values: Vec<String>,
+    /// What kind of values the argument takes, if it takes any
+    #[serde(default)]
+    pub value_hint: ValueHint,
+    /// The values used when the argument isn't present
+    #[serde(default)]
+    pub default_values: Vec<String>,
+    /// The environment variable the value is read from when the argument isn't present
+    #[serde(default)]
+    pub env: Option<String>,
+    /// The position of a positional argument, starting at 1
+    #[serde(default)]
+    pub index: Option<u64>,
+    /// Whether the argument must be present
+    #[serde(default)]
+    pub required: bool,
+    /// Whether the argument may be given more than once
+    #[serde(default)]
+    pub multiple_occurrences: bool,
+    /// Whether the argument accepts more than one value per occurrence
+    #[serde(default)]
+    pub multiple_values: bool,
+    /// The minimum number of values per occurrence, if limited (`0` makes the value optional)
+    #[serde(default)]
+    pub min_values: Option<u64>,
+    /// Whether the value must be given with `=` (i.e. `--color=auto`)
+    #[serde(default)]
+    pub require_equals: bool,
+    /// Whether the argument is propagated to all subcommands
+    #[serde(default)]
+    pub global: bool,
+    /// Whether the argument is hidden from the help message
+    #[serde(default)]
+    pub hidden: bool,
+    /// The names of the arguments of the same command this one conflicts with
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+}
+
+impl CommandSpec {
+    /// Builds the description of `app` and all of its subcommands.
+    ///
+    /// **NOTE:** Arguments which `clap` only adds when building the `App` (such as `--help`) and
+    /// propagated global arguments are only included if the `App` has already been built, which
+    /// is always the case for the `App`s handed to the generators.
+    pub fn from_app(app: &App) -> Self {
+        let bin_name = app.bin_name.clone().unwrap_or_else(|| app.name.clone());
+        CommandSpec::from_app_with_bin_name(app, bin_name)
+    }
+
+    fn from_app_with_bin_name(app: &App, bin_name: String) -> Self {
+        debugln!("CommandSpec::from_app: bin_name={}", bin_name);
+        let subcommands = app
+            .subcommands
+            .iter()
+            .map(|sc| {
+                let sc_bin_name = sc
+                    .bin_name
+                    .clone()
+                    .unwrap_or_else(|| format!("{} {}", bin_name, sc.name));
+                CommandSpec::from_app_with_bin_name(sc, sc_bin_name)
+            })
+            .collect();
+
+        CommandSpec {
+            name: app.name.clone(),
+            bin_name: bin_name,
+            bin_aliases: vec![],
+            about: app.about.map(String::from),
+            long_about: app.long_about.map(String::from),
+            author: app.author.map(String::from),
+            version: app.version.map(String::from),
+            before_help: app.pre_help.map(String::from),
+            after_help: app.more_help.map(String::from),
+            aliases: aliases_of(&app.aliases),
+            hidden: app.is_set(AppSettings::Hidden),
+            args: app.args.iter().map(|a| ArgSpec::from_arg(a).with_groups_resolved(app)).collect(),
+            subcommands: subcommands,
+        }
+    }
+
+    /// Loads a description previously written by [`to_json`](#method.to_json) (or by hand, see
+    /// [the schema](#json)). Missing `bin_name`s are derived from the names of the parent
+    /// commands, and the description is [validated](#method.validate).
+    pub fn from_json(json: &str) -> Result<Self> {
+        let doc = serde_json::from_str(json).map_err(|e| Error::InvalidSpec(e.to_string()))?;
+        CommandSpec::from_json_value(doc)
+    }
+
+    /// Like [`from_json`](#method.from_json), but reads the JSON from `reader`
+    pub fn from_json_reader<R: Read>(reader: R) -> Result<Self> {
+        let doc = serde_json::from_reader(reader).map_err(|e| Error::InvalidSpec(e.to_string()))?;
+        CommandSpec::from_json_value(doc)
+    }
+
+    /// Like [`from_json`](#method.from_json), but for the same document written as YAML
+    ///
+    /// **NOTE:** requires the `yaml` feature
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        let doc = serde_yaml::from_str(yaml).map_err(|e| Error::InvalidSpec(e.to_string()))?;
+        CommandSpec::from_json_value(doc)
+    }
+
+    fn from_json_value(mut doc: Value) -> Result<Self> {
+        match doc.get("version").and_then(Value::as_u64) {
+            Some(SPEC_VERSION) => (),
+            Some(v) => {
+                return Err(Error::InvalidSpec(format!(
+                    "unsupported version {} (expected {})",
+                    v, SPEC_VERSION
+                )))
+            }
+            None => return Err(Error::InvalidSpec("missing 'version'".into())),
+        }
+        let command = match doc.get_mut("command") {
+            Some(c) => c.take(),
+            None => return Err(Error::InvalidSpec("missing 'command'".into())),
+        };
+        let mut spec: CommandSpec =
+            serde_json::from_value(command).map_err(|e| Error::InvalidSpec(e.to_string()))?;
+        if spec.bin_name.is_empty() {
+            spec.bin_name = spec.name.clone();
+        }
+        spec.fill_bin_names();
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    /// Sets the [`ValueHint`](./enum.ValueHint.html) of the argument `arg` of the subcommand at
+    /// `path` (the space delimited names of the subcommands below this one, i.e. `"remote add"`,
+    /// or `""` for this command itself)
+    pub fn set_value_hint(&mut self, path: &str, arg: &str, hint: ValueHint) -> Result<()> {
+        let mut cmd = self;
+        for name in path.split_whitespace() {
+            cmd = match cmd.subcommands.iter_mut().find(|sc| sc.name == name) {
+                Some(sc) => sc,
+                None => {
+                    return Err(Error::MalformedApp(format!("unknown subcommand '{}'", path)))
+                }
+            };
+        }
+        match cmd.args.iter_mut().find(|a| a.name == arg) {
+            Some(a) => {
+                a.value_hint = hint;
+                Ok(())
+            }
+            None => Err(Error::MalformedApp(format!(
+                "command '{}' has no argument '{}'",
+                cmd.bin_name, arg
+            ))),
+        }
+    }
+
+    /// Renames the command to `bin_name`, updating the `bin_name`s of all of its subcommands to
+    /// match (i.e. `myapp remote add` becomes `mt remote add` for `mt`)
+    pub fn set_bin_name<S: Into<String>>(&mut self, bin_name: S) {
+        self.bin_name = bin_name.into();
+        for sc in &mut self.subcommands {
+            let sc_bin_name = format!("{} {}", self.bin_name, sc.name);
+            sc.set_bin_name(sc_bin_name);
+        }
+    }
+
+    /// Removes the hidden arguments and subcommands at any depth, which is what the generators
+    /// do before generating anything. See [`unhide`](#method.unhide) to keep them instead.
+    pub fn remove_hidden(&mut self) {
+        let hidden: Vec<String> = self.args
+            .iter()
+            .filter(|a| a.hidden)
+            .map(|a| a.name.clone())
+            .collect();
+        self.args.retain(|a| !a.hidden);
+        for arg in &mut self.args {
+            arg.conflicts.retain(|c| !hidden.contains(c));
+        }
+        self.subcommands.retain(|sc| !sc.hidden);
+        for sc in &mut self.subcommands {
+            sc.remove_hidden();
+        }
+    }
+
+    /// Makes all hidden arguments and subcommands at any depth visible, so that the generators
+    /// include them, i.e. for the completions of an internal build
+    pub fn unhide(&mut self) {
+        self.hidden = false;
+        for arg in &mut self.args {
+            arg.hidden = false;
+        }
+        for sc in &mut self.subcommands {
+            sc.unhide();
+        }
+    }
+
+    fn fill_bin_names(&mut self) {
+        for sc in &mut self.subcommands {
+            if sc.bin_name.is_empty() {
+                sc.bin_name = format!("{} {}", self.bin_name, sc.name);
+            }
+            sc.fill_bin_names();
+        }
+    }
+
+    /// Serializes the description to pretty printed JSON, wrapped in a document which records
+    /// the [`SPEC_VERSION`](./constant.SPEC_VERSION.html)
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.json_document()).expect(INTERNAL_ERROR_MSG)
+    }
+
+    /// Like [`to_json`](#method.to_json), but writes the JSON to `writer`
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(writer, &self.json_document()).map_err(|e| {
+            if e.is_io() {
+                Error::Io(e.into())
+            } else {
+                Error::InvalidSpec(e.to_string())
+            }
+        })
+    }
+
+    fn json_document<'a>(&'a self) -> JsonDocument<'a> {
+        JsonDocument {
+            version: SPEC_VERSION,
+            command: self,
+        }
+    }
+
+    /// Checks that the description is consistent, i.e. that this command and all of its
+    /// subcommands have a `bin_name`, and that every argument named in a conflict exists.
+    ///
+    /// **NOTE:** Besides the arguments of the same command, a conflict may name a global argument
+    /// of a parent command, and a (propagated) global argument may name any argument of a parent
+    /// command. Conflicts with an `ArgGroup` are already replaced by the members of the group.
+    pub fn validate(&self) -> Result<()> { self.validate_below(&[]) }
+
+    fn validate_below(&self, ancestors: &[&CommandSpec]) -> Result<()> {
+        debugln!("CommandSpec::validate: bin_name={}", self.bin_name);
+        if self.bin_name.is_empty() {
+            return Err(Error::MalformedApp(format!("command '{}' has no bin_name", self.name)));
+        }
+        for arg in &self.args {
+            for name in &arg.conflicts {
+                let known = self.find_arg(name).is_some()
+                    || ancestors.iter().any(|p| {
+                        p.find_arg(name).map_or(false, |target| target.global || arg.global)
+                    });
+                if !known {
+                    return Err(Error::MalformedApp(format!(
+                        "argument '{}' of command '{}' conflicts with unknown argument '{}'",
+                        arg.name, self.bin_name, name
+                    )));
+                }
+            }
+        }
+        let mut ancestors = ancestors.to_vec();
+        ancestors.push(self);
+        for sc in &self.subcommands {
+            sc.validate_below(&ancestors)?;
+        }
+        Ok(())
+    }
+
+    /// Iterates over all arguments which take no value
+    pub fn flags(&self) -> impl Iterator<Item = &ArgSpec> {
+        self.args.iter().filter(|a| a.kind == ArgKind::Flag)
+    }
+
+    /// Iterates over all arguments which take a value and are identified by a switch
+    pub fn opts(&self) -> impl Iterator<Item = &ArgSpec> {
+        self.args.iter().filter(|a| a.kind == ArgKind::Option)
+    }
+
+    /// Iterates over all positional arguments
+    pub fn positionals(&self) -> impl Iterator<Item = &ArgSpec> {
+        self.args.iter().filter(|a| a.kind == ArgKind::Positional)
+    }
+
+    /// Gets this command followed by all of its subcommands at any depth, each command before its
+    /// own subcommands
+    pub fn all_commands(&self) -> Vec<&CommandSpec> {
+        let mut ret = vec![self];
+        for sc in &self.subcommands {
+            ret.extend(sc.all_commands());
+        }
+        ret
+    }
+
+    /// The `bin_name` followed by all `bin_aliases`
+    pub fn bin_names(&self) -> Vec<&str> {
+        Some(&*self.bin_name)
+            .into_iter()
+            .chain(self.bin_aliases.iter().map(|n| &**n))
+            .collect()
+    }
+
+    /// Whether the command has any subcommands
+    pub fn has_subcommands(&self) -> bool { !self.subcommands.is_empty() }
+
+    /// Finds an argument of this command by its name
+    pub fn find_arg(&self, name: &str) -> Option<&ArgSpec> {
+        self.args.iter().find(|a| a.name == name)
+    }
+
+    /// Finds a flag or an option of this command by its long switch or one of its aliases
+    pub fn find_long(&self, long: &str) -> Option<&ArgSpec> {
+        self.args
+            .iter()
+            .find(|a| a.kind != ArgKind::Positional && a.longs().contains(&long))
+    }
+
+    /// Finds a direct subcommand by its name or one of its aliases
+    pub fn find_subcommand(&self, name: &str) -> Option<&CommandSpec> {
+        self.subcommands
+            .iter()
+            .find(|sc| sc.name == name || sc.aliases.iter().any(|a| a.name == name))
+    }
+
+    /// Finds a nested subcommand by the names (or aliases) along its path, i.e. `["remote",
+    /// "add"]` for `myapp remote add`
+    pub fn find_subcommand_by_path<I, S>(&self, path: I) -> Option<&CommandSpec>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut p = self;
+        for name in path {
+            p = p.find_subcommand(name.as_ref())?;
+        }
+        Some(p)
+    }
+
+    /// The names of the direct subcommands, followed by all of their aliases
+    pub fn subcommand_names(&self) -> Vec<&str> {
+        self.subcommands
+            .iter()
+            .map(|sc| &*sc.name)
+            .chain(
+                self.subcommands
+                    .iter()
+                    .flat_map(|sc| sc.aliases.iter().map(|a| &*a.name)),
+            )
+            .collect()
+    }
+}
+
+impl ArgSpec {
+    /// Builds the description of a single `clap::Arg`
+    pub fn from_arg(arg: &Arg) -> Self {
+        let kind = if arg.short.is_none() && arg.long.is_none() {
+            ArgKind::Positional
+        } else if arg.is_set(ArgSettings::TakesValue) {
+            ArgKind::Option
+        } else {
+            ArgKind::Flag
+        };
+
+        let value_names: Vec<String> = arg
+            .val_names
+            .as_ref()
+            .map_or(vec![], |names| names.values().map(|n| n.to_string()).collect());
+        let value_hint = match value_names.first() {
+            Some(name) if kind != ArgKind::Flag => ValueHint::from_value_name(name),
+            _ => ValueHint::Unknown,
+        };
+
+        ArgSpec {
+            name: arg.name.to_owned(),
+            kind: kind,
+            short: arg.short,
+            long: arg.long.map(String::from),
+            aliases: aliases_of(&arg.aliases),
+            help: arg.help.map(String::from),
+            long_help: arg.long_help.map(String::from),
+            heading: arg.help_heading.map(String::from),
+            value_names: value_names,
+            value_hint: value_hint,
+            possible_values: arg
+                .possible_vals
+                .as_ref()
+                .map_or(vec![], |vals| vals.iter().map(|v| v.to_string()).collect()),
+            default_values: arg
+                .default_val
+                .map_or(vec![], |v| vec![v.to_string_lossy().into_owned()]),
+            env: arg
+                .env
+                .as_ref()
+                .map(|&(name, _)| name.to_string_lossy().into_owned()),
+            index: arg.index,
+            required: arg.is_set(ArgSettings::Required),
+            multiple_occurrences: arg.is_set(ArgSettings::MultipleOccurrences),
+            multiple_values: arg.is_set(ArgSettings::MultipleValues),
+            min_values: arg.min_vals,
+            require_equals: arg.is_set(ArgSettings::RequireEquals),
+            global: arg.is_set(ArgSettings::Global),
+            hidden: arg.is_set(ArgSettings::Hidden),
+            conflicts: arg
+                .blacklist
+                .as_ref()
+                .map_or(vec![], |names| names.iter().map(|n| n.to_string()).collect()),
+        }
+    }
+
+    // `conflicts_with` may also name an `ArgGroup` of `app`, which is replaced by its members
+    fn with_groups_resolved(mut self, app: &App) -> Self {
+        let mut conflicts = vec![];
+        for name in self.conflicts.drain(..) {
+            let members: Vec<String> = app
+                .args
+                .iter()
+                .filter(|a| a.groups.as_ref().map_or(false, |g| g.contains(&&*name)))
+                .map(|a| a.name)
+                .chain(
+                    app.groups
+                        .iter()
+                        .filter(|g| g.name == name)
+                        .flat_map(|g| g.args.iter().cloned()),
+                )
+                .map(String::from)
+                .collect();
+            if members.is_empty() && !app.groups.iter().any(|g| g.name == name) {
+                conflicts.push(name);
+                continue;
+            }
+            for m in members {
+                if m != self.name && !conflicts.contains(&m) {
+                    conflicts.push(m);
+                }
+            }
+        }
+        self.conflicts = conflicts;
+        self
+    }
+
+    /// Whether the argument takes a value, i.e. is an option or a positional argument
+    pub fn takes_value(&self) -> bool { self.kind != ArgKind::Flag }
+
+    /// The long switch followed by all long aliases
+    pub fn longs(&self) -> Vec<&str> {
+        self.long
+            .iter()
+            .map(|l| &**l)
+            .chain(self.aliases.iter().map(|a| &*a.name))
+            .collect()
+    }
+}
+
+impl Alias {
+    /// Creates a new alias
+    pub fn new<S: Into<String>>(name: S, visible: bool) -> Self {
+        Alias {
+            name: name.into(),
+            visible: visible,
+        }
+    }
+}
+
+// The versioned envelope around a serialized CommandSpec
+#[derive(Serialize)]
+struct JsonDocument<'a> {
+    version: u64,
+    command: &'a CommandSpec,
+}
+
+fn aliases_of(aliases: &Option<Vec<(&str, bool)>>) -> Vec<Alias> {
+    aliases.as_ref().map_or(vec![], |v| {
+        v.iter().map(|&(name, vis)| Alias::new(name, vis)).collect()
+    })
+}
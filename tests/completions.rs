@@ -5,85 +5,172 @@ extern crate regex;
 use clap_generate::*;
 use clap::{App, Arg};
 use regex::Regex;
+use std::io::Write;
 
 static BASH: &'static str = r#"_myapp() {
-    local i cur prev opts cmds
+    local i cur prev words cword opts cmd skip prefix used pos
     COMPREPLY=()
-    cur="${COMP_WORDS[COMP_CWORD]}"
-    prev="${COMP_WORDS[COMP_CWORD-1]}"
-    cmd=""
+    if declare -F _get_comp_words_by_ref >/dev/null 2>&1 ; then
+        _get_comp_words_by_ref -n =: cur prev words cword
+    else
+        cur="${COMP_WORDS[COMP_CWORD]}"
+        prev="${COMP_WORDS[COMP_CWORD-1]}"
+        words=("${COMP_WORDS[@]}")
+        cword=${COMP_CWORD}
+        if [[ ${cur} == "=" ]] ; then
+            cur=""
+        elif [[ ${prev} == "=" ]] ; then
+            prev="${COMP_WORDS[COMP_CWORD-2]}"
+        fi
+    fi
+    cmd="myapp"
     opts=""
+    skip=""
+    prefix=""
+    used=""
+    pos=0
 
-    for i in ${COMP_WORDS[@]}
+    for i in "${words[@]:1:cword-1}"
     do
-        case "${i}" in
-            myapp)
-                cmd="myapp"
+        if [[ ${i} == "=" ]] ; then
+            skip=1
+            continue
+        fi
+        if [[ -n "${skip}" ]] ; then
+            skip=""
+            continue
+        fi
+        case "${cmd},${i}" in
+            myapp,test)
+                cmd="myapp__test"
+                used=""
+                pos=0
                 ;;
-            
-            help)
-                cmd+="__help"
+            myapp,help)
+                cmd="myapp__help"
+                used=""
+                pos=0
                 ;;
-            test)
-                cmd+="__test"
+            myapp__test,--case)
+                used+=" ${i}"
+                skip=1
+                ;;
+            *,-*)
+                used+=" ${i%%=*}"
                 ;;
             *)
+                pos=$((pos + 1))
                 ;;
         esac
     done
 
+    if [[ ${cur} == --*=* ]] ; then
+        prev="${cur%%=*}"
+        cur="${cur#*=}"
+        if [[ ${COMP_WORDBREAKS} != *=* ]] ; then
+            prefix="${prev}="
+        fi
+    fi
+
     case "${cmd}" in
         myapp)
-            opts=" -h -V  --help --version  <file>  test help"
-            if [[ ${cur} == -* || ${COMP_CWORD} -eq 1 ]] ; then
+            opts=" -h -V --help --version test help "
+            for i in ${used}
+            do
+                case "${i}" in
+                    -h|--help)
+                        opts="${opts/ -h / }"
+                        opts="${opts/ --help / }"
+                        ;;
+                    -V|--version)
+                        opts="${opts/ -V / }"
+                        opts="${opts/ --version / }"
+                        ;;
+                esac
+            done
+            if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-                return 0
+            else
+                case "${prev}" in
+                    *)
+                        COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
+                        case "${pos}" in
+                            0)
+                                COMPREPLY+=($(compgen -f ${cur}))
+                                ;;
+                        esac
+                        ;;
+                esac
             fi
-            case "${prev}" in
-                
-                *)
-                    COMPREPLY=()
-                    ;;
-            esac
-            COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-            return 0
             ;;
-        
-        myapp__help)
-            opts=" -h -V  --help --version  "
-            if [[ ${cur} == -* || ${COMP_CWORD} -eq 2 ]] ; then
+        myapp__test)
+            opts=" -h -V --case --help --version "
+            for i in ${used}
+            do
+                case "${i}" in
+                    -h|--help)
+                        opts="${opts/ -h / }"
+                        opts="${opts/ --help / }"
+                        ;;
+                    -V|--version)
+                        opts="${opts/ -V / }"
+                        opts="${opts/ --version / }"
+                        ;;
+                    --case)
+                        opts="${opts/ --case / }"
+                        ;;
+                esac
+            done
+            if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-                return 0
+            else
+                case "${prev}" in
+                    --case)
+                        COMPREPLY=($(compgen -f ${cur}))
+                        ;;
+                    *)
+                        COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
+                        ;;
+                esac
             fi
-            case "${prev}" in
-                
-                *)
-                    COMPREPLY=()
-                    ;;
-            esac
-            COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-            return 0
             ;;
-        myapp__test)
-            opts=" -h -V  --case --help --version  "
-            if [[ ${cur} == -* || ${COMP_CWORD} -eq 2 ]] ; then
+        myapp__help)
+            opts=" -h -V --help --version "
+            for i in ${used}
+            do
+                case "${i}" in
+                    -h|--help)
+                        opts="${opts/ -h / }"
+                        opts="${opts/ --help / }"
+                        ;;
+                    -V|--version)
+                        opts="${opts/ -V / }"
+                        opts="${opts/ --version / }"
+                        ;;
+                esac
+            done
+            if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-                return 0
+            else
+                case "${prev}" in
+                    *)
+                        COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
+                        ;;
+                esac
             fi
-            case "${prev}" in
-                
-                --case)
-                    COMPREPLY=($(compgen -f ${cur}))
-                    return 0
-                    ;;
-                *)
-                    COMPREPLY=()
-                    ;;
-            esac
-            COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-            return 0
             ;;
     esac
+
+    if [[ -n "${prefix}" ]] ; then
+        COMPREPLY=( "${COMPREPLY[@]/#/${prefix}}" )
+    fi
+    if declare -F __ltrim_colon_completions >/dev/null 2>&1 ; then
+        __ltrim_colon_completions "${prefix}${cur}"
+    fi
+    if [[ ${#COMPREPLY[@]} -eq 1 && ${COMPREPLY[0]} == *= ]] ; then
+        compopt -o nospace 2>/dev/null
+    fi
+    return 0
 }
 
 complete -F _myapp -o bashdefault -o default myapp
@@ -167,15 +254,44 @@ _myapp__test_commands() {
 
 _myapp "$@""#;
 
-static FISH: &'static str = r#"complete -c myapp -n "__fish_use_subcommand" -s h -l help -d 'Prints help information'
-complete -c myapp -n "__fish_use_subcommand" -s V -l version -d 'Prints version information'
-complete -c myapp -n "__fish_use_subcommand" -f -a "test" -d 'tests things'
-complete -c myapp -n "__fish_use_subcommand" -f -a "help" -d 'Prints this message or the help of the given subcommand(s)'
-complete -c myapp -n "__fish_seen_subcommand_from test" -l case -d 'the case to test'
-complete -c myapp -n "__fish_seen_subcommand_from test" -s h -l help -d 'Prints help information'
-complete -c myapp -n "__fish_seen_subcommand_from test" -s V -l version -d 'Prints version information'
-complete -c myapp -n "__fish_seen_subcommand_from help" -s h -l help -d 'Prints help information'
-complete -c myapp -n "__fish_seen_subcommand_from help" -s V -l version -d 'Prints version information'
+static FISH: &'static str = r#"function __fish_myapp_subcommand_path
+    set -l cmd (commandline -opc)
+    set -e cmd[1]
+    set -l path
+    set -l skip 0
+    for word in $cmd
+        if test $skip = 1
+            set skip 0
+            continue
+        end
+        switch "$path;$word"
+            case '*;--'
+                break
+            case ';test'
+                set path 'test'
+            case 'test;--case'
+                set skip 1
+            case ';help'
+                set path 'help'
+        end
+    end
+    echo $path
+end
+
+function __fish_myapp_using_subcommand
+    set -l path (__fish_myapp_subcommand_path)
+    test "$path" = "$argv"
+end
+
+complete -c myapp -n "__fish_myapp_using_subcommand; and not __fish_contains_opt -s h help" -s h -l help -d 'Prints help information' -f
+complete -c myapp -n "__fish_myapp_using_subcommand; and not __fish_contains_opt -s V version" -s V -l version -d 'Prints version information' -f
+complete -c myapp -n "__fish_myapp_using_subcommand" -f -a "test" -d 'tests things'
+complete -c myapp -n "__fish_myapp_using_subcommand" -f -a "help" -d 'Prints this message or the help of the given subcommand(s)'
+complete -c myapp -n "__fish_myapp_using_subcommand test; and not __fish_contains_opt case" -l case -d 'the case to test' -r
+complete -c myapp -n "__fish_myapp_using_subcommand test; and not __fish_contains_opt -s h help" -s h -l help -d 'Prints help information' -f
+complete -c myapp -n "__fish_myapp_using_subcommand test; and not __fish_contains_opt -s V version" -s V -l version -d 'Prints version information' -f
+complete -c myapp -n "__fish_myapp_using_subcommand help; and not __fish_contains_opt -s h help" -s h -l help -d 'Prints help information' -f
+complete -c myapp -n "__fish_myapp_using_subcommand help; and not __fish_contains_opt -s V version" -s V -l version -d 'Prints version information' -f
 "#;
 
 static POWERSHELL: &'static str = r#"
@@ -509,155 +625,306 @@ _my_app__test_commands() {
 
 _my_app "$@""#;
 
-static FISH_SPECIAL_CMDS: &'static str = r#"complete -c my_app -n "__fish_use_subcommand" -s h -l help -d 'Prints help information'
-complete -c my_app -n "__fish_use_subcommand" -s V -l version -d 'Prints version information'
-complete -c my_app -n "__fish_use_subcommand" -f -a "test" -d 'tests things'
-complete -c my_app -n "__fish_use_subcommand" -f -a "some_cmd" -d 'tests other things'
-complete -c my_app -n "__fish_use_subcommand" -f -a "some-cmd-with-hypens"
-complete -c my_app -n "__fish_use_subcommand" -f -a "help" -d 'Prints this message or the help of the given subcommand(s)'
-complete -c my_app -n "__fish_seen_subcommand_from test" -l case -d 'the case to test'
-complete -c my_app -n "__fish_seen_subcommand_from test" -s h -l help -d 'Prints help information'
-complete -c my_app -n "__fish_seen_subcommand_from test" -s V -l version -d 'Prints version information'
-complete -c my_app -n "__fish_seen_subcommand_from some_cmd" -l config -d 'the other case to test'
-complete -c my_app -n "__fish_seen_subcommand_from some_cmd" -s h -l help -d 'Prints help information'
-complete -c my_app -n "__fish_seen_subcommand_from some_cmd" -s V -l version -d 'Prints version information'
-complete -c my_app -n "__fish_seen_subcommand_from some-cmd-with-hypens" -s h -l help -d 'Prints help information'
-complete -c my_app -n "__fish_seen_subcommand_from some-cmd-with-hypens" -s V -l version -d 'Prints version information'
-complete -c my_app -n "__fish_seen_subcommand_from help" -s h -l help -d 'Prints help information'
-complete -c my_app -n "__fish_seen_subcommand_from help" -s V -l version -d 'Prints version information'
+static FISH_SPECIAL_CMDS: &'static str = r#"function __fish_my_app_subcommand_path
+    set -l cmd (commandline -opc)
+    set -e cmd[1]
+    set -l path
+    set -l skip 0
+    for word in $cmd
+        if test $skip = 1
+            set skip 0
+            continue
+        end
+        switch "$path;$word"
+            case '*;--'
+                break
+            case ';test'
+                set path 'test'
+            case 'test;--case'
+                set skip 1
+            case ';some_cmd'
+                set path 'some_cmd'
+            case 'some_cmd;--config'
+                set skip 1
+            case ';some-cmd-with-hypens'
+                set path 'some-cmd-with-hypens'
+            case ';help'
+                set path 'help'
+        end
+    end
+    echo $path
+end
+
+function __fish_my_app_using_subcommand
+    set -l path (__fish_my_app_subcommand_path)
+    test "$path" = "$argv"
+end
+
+complete -c my_app -n "__fish_my_app_using_subcommand; and not __fish_contains_opt -s h help" -s h -l help -d 'Prints help information' -f
+complete -c my_app -n "__fish_my_app_using_subcommand; and not __fish_contains_opt -s V version" -s V -l version -d 'Prints version information' -f
+complete -c my_app -n "__fish_my_app_using_subcommand" -f -a "test" -d 'tests things'
+complete -c my_app -n "__fish_my_app_using_subcommand" -f -a "some_cmd" -d 'tests other things'
+complete -c my_app -n "__fish_my_app_using_subcommand" -f -a "some-cmd-with-hypens"
+complete -c my_app -n "__fish_my_app_using_subcommand" -f -a "help" -d 'Prints this message or the help of the given subcommand(s)'
+complete -c my_app -n "__fish_my_app_using_subcommand test; and not __fish_contains_opt case" -l case -d 'the case to test' -r
+complete -c my_app -n "__fish_my_app_using_subcommand test; and not __fish_contains_opt -s h help" -s h -l help -d 'Prints help information' -f
+complete -c my_app -n "__fish_my_app_using_subcommand test; and not __fish_contains_opt -s V version" -s V -l version -d 'Prints version information' -f
+complete -c my_app -n "__fish_my_app_using_subcommand some_cmd; and not __fish_contains_opt config" -l config -d 'the other case to test' -r
+complete -c my_app -n "__fish_my_app_using_subcommand some_cmd; and not __fish_contains_opt -s h help" -s h -l help -d 'Prints help information' -f
+complete -c my_app -n "__fish_my_app_using_subcommand some_cmd; and not __fish_contains_opt -s V version" -s V -l version -d 'Prints version information' -f
+complete -c my_app -n "__fish_my_app_using_subcommand some-cmd-with-hypens; and not __fish_contains_opt -s h help" -s h -l help -d 'Prints help information' -f
+complete -c my_app -n "__fish_my_app_using_subcommand some-cmd-with-hypens; and not __fish_contains_opt -s V version" -s V -l version -d 'Prints version information' -f
+complete -c my_app -n "__fish_my_app_using_subcommand help; and not __fish_contains_opt -s h help" -s h -l help -d 'Prints help information' -f
+complete -c my_app -n "__fish_my_app_using_subcommand help; and not __fish_contains_opt -s V version" -s V -l version -d 'Prints version information' -f
 "#;
 
 static BASH_SPECIAL_CMDS: &'static str = r#"_my_app() {
-    local i cur prev opts cmds
+    local i cur prev words cword opts cmd skip prefix used pos
     COMPREPLY=()
-    cur="${COMP_WORDS[COMP_CWORD]}"
-    prev="${COMP_WORDS[COMP_CWORD-1]}"
-    cmd=""
+    if declare -F _get_comp_words_by_ref >/dev/null 2>&1 ; then
+        _get_comp_words_by_ref -n =: cur prev words cword
+    else
+        cur="${COMP_WORDS[COMP_CWORD]}"
+        prev="${COMP_WORDS[COMP_CWORD-1]}"
+        words=("${COMP_WORDS[@]}")
+        cword=${COMP_CWORD}
+        if [[ ${cur} == "=" ]] ; then
+            cur=""
+        elif [[ ${prev} == "=" ]] ; then
+            prev="${COMP_WORDS[COMP_CWORD-2]}"
+        fi
+    fi
+    cmd="my_app"
     opts=""
+    skip=""
+    prefix=""
+    used=""
+    pos=0
 
-    for i in ${COMP_WORDS[@]}
+    for i in "${words[@]:1:cword-1}"
     do
-        case "${i}" in
-            my_app)
-                cmd="my_app"
+        if [[ ${i} == "=" ]] ; then
+            skip=1
+            continue
+        fi
+        if [[ -n "${skip}" ]] ; then
+            skip=""
+            continue
+        fi
+        case "${cmd},${i}" in
+            my_app,test)
+                cmd="my_app__test"
+                used=""
+                pos=0
+                ;;
+            my_app,some_cmd)
+                cmd="my_app__some_cmd"
+                used=""
+                pos=0
                 ;;
-            
-            help)
-                cmd+="__help"
+            my_app,some-cmd-with-hypens)
+                cmd="my_app__some__cmd__with__hypens"
+                used=""
+                pos=0
                 ;;
-            some-cmd-with-hypens)
-                cmd+="__some__cmd__with__hypens"
+            my_app,help)
+                cmd="my_app__help"
+                used=""
+                pos=0
                 ;;
-            some_cmd)
-                cmd+="__some_cmd"
+            my_app__test,--case)
+                used+=" ${i}"
+                skip=1
                 ;;
-            test)
-                cmd+="__test"
+            my_app__some_cmd,--config)
+                used+=" ${i}"
+                skip=1
+                ;;
+            *,-*)
+                used+=" ${i%%=*}"
                 ;;
             *)
+                pos=$((pos + 1))
                 ;;
         esac
     done
 
+    if [[ ${cur} == --*=* ]] ; then
+        prev="${cur%%=*}"
+        cur="${cur#*=}"
+        if [[ ${COMP_WORDBREAKS} != *=* ]] ; then
+            prefix="${prev}="
+        fi
+    fi
+
     case "${cmd}" in
         my_app)
-            opts=" -h -V  --help --version  <file>  test some_cmd some-cmd-with-hypens help"
-            if [[ ${cur} == -* || ${COMP_CWORD} -eq 1 ]] ; then
+            opts=" -h -V --help --version test some_cmd some-cmd-with-hypens help "
+            for i in ${used}
+            do
+                case "${i}" in
+                    -h|--help)
+                        opts="${opts/ -h / }"
+                        opts="${opts/ --help / }"
+                        ;;
+                    -V|--version)
+                        opts="${opts/ -V / }"
+                        opts="${opts/ --version / }"
+                        ;;
+                esac
+            done
+            if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-                return 0
+            else
+                case "${prev}" in
+                    *)
+                        COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
+                        case "${pos}" in
+                            0)
+                                COMPREPLY+=($(compgen -f ${cur}))
+                                ;;
+                        esac
+                        ;;
+                esac
             fi
-            case "${prev}" in
-                
-                *)
-                    COMPREPLY=()
-                    ;;
-            esac
-            COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-            return 0
             ;;
-        
-        my_app__help)
-            opts=" -h -V  --help --version  "
-            if [[ ${cur} == -* || ${COMP_CWORD} -eq 2 ]] ; then
+        my_app__test)
+            opts=" -h -V --case --help --version "
+            for i in ${used}
+            do
+                case "${i}" in
+                    -h|--help)
+                        opts="${opts/ -h / }"
+                        opts="${opts/ --help / }"
+                        ;;
+                    -V|--version)
+                        opts="${opts/ -V / }"
+                        opts="${opts/ --version / }"
+                        ;;
+                    --case)
+                        opts="${opts/ --case / }"
+                        ;;
+                esac
+            done
+            if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-                return 0
+            else
+                case "${prev}" in
+                    --case)
+                        COMPREPLY=($(compgen -f ${cur}))
+                        ;;
+                    *)
+                        COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
+                        ;;
+                esac
             fi
-            case "${prev}" in
-                
-                *)
-                    COMPREPLY=()
-                    ;;
-            esac
-            COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-            return 0
             ;;
-        my_app__some__cmd__with__hypens)
-            opts=" -h -V  --help --version  "
-            if [[ ${cur} == -* || ${COMP_CWORD} -eq 2 ]] ; then
+        my_app__some_cmd)
+            opts=" -h -V --config --help --version "
+            for i in ${used}
+            do
+                case "${i}" in
+                    -h|--help)
+                        opts="${opts/ -h / }"
+                        opts="${opts/ --help / }"
+                        ;;
+                    -V|--version)
+                        opts="${opts/ -V / }"
+                        opts="${opts/ --version / }"
+                        ;;
+                    --config)
+                        opts="${opts/ --config / }"
+                        ;;
+                esac
+            done
+            if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-                return 0
+            else
+                case "${prev}" in
+                    --config)
+                        COMPREPLY=($(compgen -f ${cur}))
+                        ;;
+                    *)
+                        COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
+                        ;;
+                esac
             fi
-            case "${prev}" in
-                
-                *)
-                    COMPREPLY=()
-                    ;;
-            esac
-            COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-            return 0
             ;;
-        my_app__some_cmd)
-            opts=" -h -V  --config --help --version  "
-            if [[ ${cur} == -* || ${COMP_CWORD} -eq 2 ]] ; then
+        my_app__some__cmd__with__hypens)
+            opts=" -h -V --help --version "
+            for i in ${used}
+            do
+                case "${i}" in
+                    -h|--help)
+                        opts="${opts/ -h / }"
+                        opts="${opts/ --help / }"
+                        ;;
+                    -V|--version)
+                        opts="${opts/ -V / }"
+                        opts="${opts/ --version / }"
+                        ;;
+                esac
+            done
+            if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-                return 0
+            else
+                case "${prev}" in
+                    *)
+                        COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
+                        ;;
+                esac
             fi
-            case "${prev}" in
-                
-                --config)
-                    COMPREPLY=($(compgen -f ${cur}))
-                    return 0
-                    ;;
-                *)
-                    COMPREPLY=()
-                    ;;
-            esac
-            COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-            return 0
             ;;
-        my_app__test)
-            opts=" -h -V  --case --help --version  "
-            if [[ ${cur} == -* || ${COMP_CWORD} -eq 2 ]] ; then
+        my_app__help)
+            opts=" -h -V --help --version "
+            for i in ${used}
+            do
+                case "${i}" in
+                    -h|--help)
+                        opts="${opts/ -h / }"
+                        opts="${opts/ --help / }"
+                        ;;
+                    -V|--version)
+                        opts="${opts/ -V / }"
+                        opts="${opts/ --version / }"
+                        ;;
+                esac
+            done
+            if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-                return 0
+            else
+                case "${prev}" in
+                    *)
+                        COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
+                        ;;
+                esac
             fi
-            case "${prev}" in
-                
-                --case)
-                    COMPREPLY=($(compgen -f ${cur}))
-                    return 0
-                    ;;
-                *)
-                    COMPREPLY=()
-                    ;;
-            esac
-            COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
-            return 0
             ;;
     esac
+
+    if [[ -n "${prefix}" ]] ; then
+        COMPREPLY=( "${COMPREPLY[@]/#/${prefix}}" )
+    fi
+    if declare -F __ltrim_colon_completions >/dev/null 2>&1 ; then
+        __ltrim_colon_completions "${prefix}${cur}"
+    fi
+    if [[ ${#COMPREPLY[@]} -eq 1 && ${COMPREPLY[0]} == *= ]] ; then
+        compopt -o nospace 2>/dev/null
+    fi
+    return 0
 }
 
 complete -F _my_app -o bashdefault -o default my_app
 "#;
 
-static FISH_SPECIAL_HELP: &'static str = r#"complete -c my_app -n "__fish_use_subcommand" -l single-quotes -d 'Can be \'always\', \'auto\', or \'never\''
-complete -c my_app -n "__fish_use_subcommand" -l double-quotes -d 'Can be "always", "auto", or "never"'
-complete -c my_app -n "__fish_use_subcommand" -l backticks -d 'For more information see `echo test`'
-complete -c my_app -n "__fish_use_subcommand" -l backslash -d 'Avoid \'\\n\''
-complete -c my_app -n "__fish_use_subcommand" -l brackets -d 'List packages [filter]'
-complete -c my_app -n "__fish_use_subcommand" -l expansions -d 'Execute the shell command with $SHELL'
-complete -c my_app -n "__fish_use_subcommand" -s h -l help -d 'Prints help information'
-complete -c my_app -n "__fish_use_subcommand" -s V -l version -d 'Prints version information'
+static FISH_SPECIAL_HELP: &'static str = r#"complete -c my_app -n "not __fish_contains_opt single-quotes" -l single-quotes -d 'Can be \'always\', \'auto\', or \'never\'' -f
+complete -c my_app -n "not __fish_contains_opt double-quotes" -l double-quotes -d 'Can be "always", "auto", or "never"' -f
+complete -c my_app -n "not __fish_contains_opt backticks" -l backticks -d 'For more information see `echo test`' -f
+complete -c my_app -n "not __fish_contains_opt backslash" -l backslash -d 'Avoid \'\\n\'' -f
+complete -c my_app -n "not __fish_contains_opt brackets" -l brackets -d 'List packages [filter]' -f
+complete -c my_app -n "not __fish_contains_opt expansions" -l expansions -d 'Execute the shell command with $SHELL' -f
+complete -c my_app -n "not __fish_contains_opt -s h help" -s h -l help -d 'Prints help information' -f
+complete -c my_app -n "not __fish_contains_opt -s V version" -s V -l version -d 'Prints version information' -f
 "#;
 
 static ZSH_SPECIAL_HELP: &'static str = r#"#compdef my_app
@@ -701,6 +968,388 @@ _my_app_commands() {
 
 _my_app "$@""#;
 
+static ZSH_HEADINGS: &'static str = r#"#compdef my_app
+
+autoload -U is-at-least
+
+_my_app() {
+    typeset -A opt_args
+    typeset -a _arguments_options
+    local ret=1
+
+    if is-at-least 5.2; then
+        _arguments_options=(-s -S -C)
+    else
+        _arguments_options=(-s -C)
+    fi
+
+    local context curcontext="$curcontext" state line
+    if is-at-least 5.4; then
+_arguments "${_arguments_options[@]}" \
+'--verbose[Use verbose output]' \
+'-h[Prints help information]' \
+'--help[Prints help information]' \
+'-V[Prints version information]' \
+'--version[Prints version information]' \
++ 'Networking' \
+'--port=[The port to bind to]' \
+'-6[Only use IPv6]' \
+&& ret=0
+else
+_arguments "${_arguments_options[@]}" \
+'--verbose[Use verbose output]' \
+'-h[Prints help information]' \
+'--help[Prints help information]' \
+'-V[Prints version information]' \
+'--version[Prints version information]' \
+'--port=[The port to bind to]' \
+'-6[Only use IPv6]' \
+&& ret=0
+fi
+    
+}
+
+(( $+functions[_my_app_commands] )) ||
+_my_app_commands() {
+    local commands; commands=(
+        
+    )
+    _describe -t commands 'my_app commands' commands "$@"
+}
+
+_my_app "$@""#;
+
+static FISH_HEADINGS: &'static str = r#"complete -c my_app -n "not __fish_contains_opt port" -l port -d 'Networking: The port to bind to' -r
+complete -c my_app -n "not __fish_contains_opt verbose" -l verbose -d 'Use verbose output' -f
+complete -c my_app -n "not __fish_contains_opt -s 6" -s 6 -d 'Networking: Only use IPv6' -f
+complete -c my_app -n "not __fish_contains_opt -s h help" -s h -l help -d 'Prints help information' -f
+complete -c my_app -n "not __fish_contains_opt -s V version" -s V -l version -d 'Prints version information' -f
+"#;
+
+static POWERSHELL_HEADINGS: &'static str = r#"
+using namespace System.Management.Automation
+using namespace System.Management.Automation.Language
+
+Register-ArgumentCompleter -Native -CommandName 'my_app' -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+
+    $commandElements = $commandAst.CommandElements
+    $command = @(
+        'my_app'
+        for ($i = 1; $i -lt $commandElements.Count; $i++) {
+            $element = $commandElements[$i]
+            if ($element -isnot [StringConstantExpressionAst] -or
+                $element.StringConstantType -ne [StringConstantType]::BareWord -or
+                $element.Value.StartsWith('-')) {
+                break
+        }
+        $element.Value
+    }) -join ';'
+
+    $completions = @(switch ($command) {
+        'my_app' {
+            [CompletionResult]::new('--port', 'port', [CompletionResultType]::ParameterName, 'Networking: The port to bind to')
+            [CompletionResult]::new('--verbose', 'verbose', [CompletionResultType]::ParameterName, 'Use verbose output')
+            [CompletionResult]::new('-6', '6', [CompletionResultType]::ParameterName, 'Networking: Only use IPv6')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Prints help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Prints help information')
+            [CompletionResult]::new('-V', 'V', [CompletionResultType]::ParameterName, 'Prints version information')
+            [CompletionResult]::new('--version', 'version', [CompletionResultType]::ParameterName, 'Prints version information')
+            break
+        }
+    })
+
+    $completions.Where{ $_.CompletionText -like "$wordToComplete*" } |
+        Sort-Object -Property ListItemText
+}
+"#;
+
+static NUSHELL: &'static str = r#"module completions {
+
+  # Tests completions
+  export extern "my_app" [
+    --help(-h)  # Prints help information
+    --version(-V)  # Prints version information
+    file?: string  # some input file
+  ]
+
+  # tests things
+  export extern "my_app test" [
+    --case: string  # the case to test
+    --help(-h)  # Prints help information
+    --version(-V)  # Prints version information
+  ]
+
+  # Prints this message or the help of the given subcommand(s)
+  export extern "my_app help" [
+    --help(-h)  # Prints help information
+    --version(-V)  # Prints version information
+  ]
+}
+
+export use completions *
+"#;
+
+static NUSHELL_POSSIBLE_VALUES: &'static str = r#"module completions {
+
+  def "nu-complete my_app format" [] {
+    [ "json" "yaml" ]
+  }
+
+  export extern "my_app" [
+    --format(-f): string@"nu-complete my_app format"  # the output format
+    --fmt: string@"nu-complete my_app format"  # the output format
+    --help(-h)  # Prints help information
+    --version(-V)  # Prints version information
+    ...files: string  # the "input" files
+  ]
+}
+
+export use completions *
+"#;
+
+static TCSH: &'static str = r#"complete myapp \
+    'n/--case/f/' \
+    'n/test/(--help -h --version -V --case)/' \
+    'n/help/(--help -h --version -V)/' \
+    'p/1/(test help --help -h --version -V)/' \
+    'c/--/(help version)/' \
+    'c/-/(h V -)/'
+"#;
+
+static TCSH_NESTED: &'static str = r#"complete my_app \
+    'n/--target/(debug release)/' \
+    'n/-t/(debug release)/' \
+    'n/r/(add help --help -h --version -V)/' \
+    'n/remote/(add help --help -h --version -V)/' \
+    'n/help/(--help -h --version -V)/' \
+    'n/add/(--help -h --version -V --target -t)/' \
+    'p/1/(remote help r --help -h --version -V)/' \
+    'c/--/(help version)/' \
+    'c/-/(h V -)/'
+complete mt \
+    'n/--target/(debug release)/' \
+    'n/-t/(debug release)/' \
+    'n/r/(add help --help -h --version -V)/' \
+    'n/remote/(add help --help -h --version -V)/' \
+    'n/help/(--help -h --version -V)/' \
+    'n/add/(--help -h --version -V --target -t)/' \
+    'p/1/(remote help r --help -h --version -V)/' \
+    'c/--/(help version)/' \
+    'c/-/(h V -)/'
+"#;
+
+static XONSH: &'static str = r#"from xonsh.completers.tools import RichCompletion
+
+
+_MY_APP_COMPLETIONS = {
+    'my_app': [
+        ('-h', 'Prints help information'),
+        ('--help', 'Prints help information'),
+        ('-V', 'Prints version information'),
+        ('--version', 'Prints version information'),
+        ('test', 'tests things'),
+        ('help', 'Prints this message or the help of the given subcommand(s)'),
+    ],
+    'my_app;test': [
+        ('--case', 'the case to test'),
+        ('-h', 'Prints help information'),
+        ('--help', 'Prints help information'),
+        ('-V', 'Prints version information'),
+        ('--version', 'Prints version information'),
+    ],
+    'my_app;help': [
+        ('-h', 'Prints help information'),
+        ('--help', 'Prints help information'),
+        ('-V', 'Prints version information'),
+        ('--version', 'Prints version information'),
+    ],
+}
+
+_MY_APP_VALUES = {
+}
+
+
+def _my_app_completer(prefix, line, begidx, endidx, ctx):
+    words = line[:begidx].split()
+    if not words or words[0] not in ('my_app',):
+        return None
+    command = 'my_app'
+    for word in words[1:]:
+        if word.startswith('-'):
+            break
+        command += ';' + word
+    key = command + ';' + words[-1]
+    if key in _MY_APP_VALUES:
+        candidates = _MY_APP_VALUES[key]
+    else:
+        candidates = _MY_APP_COMPLETIONS.get(command, [])
+    completions = {
+        RichCompletion(value, description=desc)
+        for value, desc in candidates
+        if value.startswith(prefix)
+    }
+    return completions or None
+
+
+completer add my_app _my_app_completer "start"
+"#;
+
+static FIG: &'static str = r#"const completion: Fig.Spec = {
+  "name": "myapp",
+  "description": "Tests completions",
+  "subcommands": [
+    {
+      "name": "test",
+      "description": "tests things",
+      "options": [
+        {
+          "name": [
+            "-h",
+            "--help"
+          ],
+          "description": "Prints help information"
+        },
+        {
+          "name": [
+            "-V",
+            "--version"
+          ],
+          "description": "Prints version information"
+        },
+        {
+          "name": "--case",
+          "description": "the case to test",
+          "args": {
+            "name": "case"
+          }
+        }
+      ]
+    },
+    {
+      "name": "help",
+      "description": "Prints this message or the help of the given subcommand(s)",
+      "options": [
+        {
+          "name": [
+            "-h",
+            "--help"
+          ],
+          "description": "Prints help information"
+        },
+        {
+          "name": [
+            "-V",
+            "--version"
+          ],
+          "description": "Prints version information"
+        }
+      ]
+    }
+  ],
+  "options": [
+    {
+      "name": [
+        "-h",
+        "--help"
+      ],
+      "description": "Prints help information"
+    },
+    {
+      "name": [
+        "-V",
+        "--version"
+      ],
+      "description": "Prints version information"
+    }
+  ],
+  "args": [
+    {
+      "name": "file",
+      "description": "some input file",
+      "isOptional": true
+    }
+  ]
+};
+
+export default completion;
+"#;
+
+static CARAPACE: &'static str = r#"name: "myapp"
+description: "Tests completions"
+flags:
+  "-h, --help": "Prints help information"
+  "-V, --version": "Prints version information"
+completion:
+  positional:
+    - ["$files"]
+commands:
+  - name: "test"
+    description: "tests things"
+    flags:
+      "-h, --help": "Prints help information"
+      "-V, --version": "Prints version information"
+      "--case=": "the case to test"
+  - name: "help"
+    description: "Prints this message or the help of the given subcommand(s)"
+    flags:
+      "-h, --help": "Prints help information"
+      "-V, --version": "Prints version information"
+"#;
+
+static CLINK: &'static str = r#"local m = {}
+m["myapp test"] = clink.argmatcher()
+    :addflags({
+        "-h",
+        "--help",
+        "-V",
+        "--version",
+        "--case" .. clink.argmatcher():addarg(clink.filematches),
+    })
+    :adddescriptions({
+        ["-h"] = "Prints help information",
+        ["--help"] = "Prints help information",
+        ["-V"] = "Prints version information",
+        ["--version"] = "Prints version information",
+        ["--case"] = "the case to test",
+    })
+
+m["myapp help"] = clink.argmatcher()
+    :addflags({
+        "-h",
+        "--help",
+        "-V",
+        "--version",
+    })
+    :adddescriptions({
+        ["-h"] = "Prints help information",
+        ["--help"] = "Prints help information",
+        ["-V"] = "Prints version information",
+        ["--version"] = "Prints version information",
+    })
+
+clink.argmatcher("myapp")
+    :addarg({
+        "test" .. m["myapp test"],
+        "help" .. m["myapp help"],
+        clink.filematches,
+    })
+    :addflags({
+        "-h",
+        "--help",
+        "-V",
+        "--version",
+    })
+    :adddescriptions({
+        ["test"] = "tests things",
+        ["help"] = "Prints this message or the help of the given subcommand(s)",
+        ["-h"] = "Prints help information",
+        ["--help"] = "Prints help information",
+        ["-V"] = "Prints version information",
+        ["--version"] = "Prints version information",
+    })
+"#;
+
 fn compare(left: &str, right: &str) -> bool {
     let b = left == right;
     if !b {
@@ -742,6 +1391,22 @@ fn build_app_special_commands() -> App<'static, 'static> {
         .subcommand(App::new("some-cmd-with-hypens"))
 }
 
+fn build_app_with_headings() -> App<'static, 'static> {
+    App::new("my_app")
+        .arg(Arg::with_name("verbose")
+            .long("verbose")
+            .help("Use verbose output"))
+        .help_heading("Networking")
+        .arg(Arg::with_name("port")
+            .long("port")
+            .takes_value(true)
+            .help("The port to bind to"))
+        .arg(Arg::with_name("ipv6")
+            .short('6')
+            .help("Only use IPv6"))
+        .stop_custom_headings()
+}
+
 fn build_app_special_help() -> App<'static, 'static> {
     App::new("my_app")
         .arg(Arg::with_name("single-quotes")
@@ -814,6 +1479,246 @@ fn elvish() {
     assert!(compare(&*string, ELVISH));
 }
 
+#[test]
+fn nushell() {
+    let mut app = build_app();
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "my_app", Shell::Nushell, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(compare(&*string, NUSHELL));
+}
+
+#[test]
+fn nushell_with_possible_values() {
+    let mut app = App::new("my_app")
+        .arg(Arg::with_name("format")
+            .short('f')
+            .long("format")
+            .alias("fmt")
+            .possible_values(&["json", "yaml"])
+            .help("the output format"))
+        .arg(Arg::with_name("files")
+            .multiple(true)
+            .help("the \"input\" files"));
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "my_app", Shell::Nushell, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(compare(&*string, NUSHELL_POSSIBLE_VALUES));
+}
+
+#[test]
+fn tcsh() {
+    let mut app = build_app();
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "myapp", Shell::Tcsh, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(compare(&*string, TCSH));
+}
+
+#[test]
+fn tcsh_shared_switches_and_special_values() {
+    let format = |values| Arg::with_name("format").long("format").possible_values(values);
+    let mut app = App::new("my_app")
+        .subcommand(App::new("a").arg(format(&["json", "yaml"])))
+        .subcommand(App::new("b").arg(format(&["yaml", "toml"])))
+        .subcommand(App::new("c").arg(Arg::with_name("out").long("out").takes_value(true)))
+        .subcommand(App::new("d").arg(Arg::with_name("out").long("out").possible_values(&["-"])))
+        .subcommand(App::new("e").arg(
+            Arg::with_name("path").long("path").possible_values(&["a/b", "c d"]),
+        ));
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "my_app", Shell::Tcsh, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(string.contains("'n/--format/(json yaml toml)/'"));
+    assert!(!string.contains("n/--out/"));
+    assert!(string.contains("'n|--path|(a/b)|'"));
+}
+
+#[test]
+fn tcsh_with_nested_subcommands() {
+    let mut spec = command_spec(
+        &mut App::new("my_app").subcommand(
+            App::new("remote").alias("r").subcommand(
+                App::new("add").arg(
+                    Arg::with_name("target")
+                        .short('t')
+                        .long("target")
+                        .possible_values(&["debug", "release"]),
+                ),
+            ),
+        ),
+        "my_app",
+    ).unwrap();
+    spec.bin_aliases.push("mt".into());
+    let mut buf = vec![];
+    generate_completions_from_spec(&spec, Shell::Tcsh, &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(compare(&*string, TCSH_NESTED));
+}
+
+#[test]
+fn xonsh() {
+    let mut app = build_app();
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "my_app", Shell::Xonsh, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(compare(&*string, XONSH));
+}
+
+#[test]
+fn xonsh_with_possible_values() {
+    let mut app = build_app_with_name("my-app")
+        .arg(Arg::with_name("format")
+            .short('f')
+            .long("format")
+            .possible_values(&["json", "it's"])
+            .help("the output format"));
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "my-app", Shell::Xonsh, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(string.contains(r#"
+_MY_APP_VALUES = {
+    'my-app;-f': [
+        ('json', 'the output format'),
+        ('it\'s', 'the output format'),
+    ],
+    'my-app;--format': ["#));
+    assert!(string.contains("def _my_app_completer("));
+    assert!(string.contains("completer add my-app _my_app_completer \"start\"\n"));
+}
+
+#[test]
+fn xonsh_with_aliases() {
+    let mut app = App::new("myapp")
+        .subcommand(App::new("remote")
+            .alias("r")
+            .subcommand(App::new("add")
+                .arg(Arg::with_name("mode").long("mode").possible_values(&["fast"]))))
+        .subcommand(App::new("it's"));
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "myapp", Shell::Xonsh, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(string.contains("\n    'myapp;remote;add': [\n"));
+    assert!(string.contains("\n    'myapp;r;add': [\n"));
+    assert!(string.contains("\n    'myapp;r;add;--mode': [\n        ('fast', ''),\n    ],"));
+    assert!(string.contains("\n        ('it\\'s', 'it\\'s'),"));
+}
+
+#[test]
+fn fig() {
+    let mut app = build_app();
+    let mut buf = vec![];
+    generate_with::<FigGen, _, _>(&mut app, "myapp", &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+
+    assert_eq!(FigGen::file_name("myapp"), "myapp.ts");
+    assert!(compare(&*string, FIG));
+}
+
+#[test]
+fn fig_with_repeatable_and_exclusive_options() {
+    let mut app = App::new("myapp")
+        .arg(Arg::with_name("verbose")
+            .short('v')
+            .multiple_occurrences(true)
+            .conflicts_with("quiet"))
+        .arg(Arg::with_name("quiet").long("quiet"))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .possible_values(&["json", "yaml"]));
+    let mut buf = vec![];
+    generate_with::<FigGen, _, _>(&mut app, "myapp", &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(string.contains(r#""name": "-v",
+      "isRepeatable": true,
+      "exclusiveOn": [
+        "--quiet"
+      ]"#));
+    assert!(string.contains(r#""name": "--quiet",
+      "exclusiveOn": [
+        "-v"
+      ]"#));
+    assert!(string.contains(r#""suggestions": [
+          "json",
+          "yaml"
+        ]"#));
+}
+
+#[test]
+fn carapace() {
+    let mut app = build_app();
+    let mut buf = vec![];
+    generate_with::<CarapaceGen, _, _>(&mut app, "myapp", &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(compare(&*string, CARAPACE));
+}
+
+#[test]
+fn carapace_flag_modifiers_and_completions() {
+    let mut app = App::new("myapp")
+        .arg(Arg::with_name("verbose").short('v').multiple_occurrences(true))
+        .arg(Arg::with_name("color").long("color").min_values(0).possible_values(&["auto", "never"]))
+        .arg(Arg::with_name("out").short('o').value_name("DIR").required(true))
+        .arg(Arg::with_name("files").multiple(true));
+    let mut buf = vec![];
+    generate_with::<CarapaceGen, _, _>(&mut app, "myapp", &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(string.contains("  \"-v*\": \"\"\n"));
+    assert!(string.contains("  \"--color?\": \"\"\n"));
+    assert!(string.contains("  \"-o=!\": \"\"\n"));
+    assert!(string.contains(r#"completion:
+  flag:
+    "color": ["auto", "never"]
+    "o": ["$directories"]
+  positionalany: ["$files"]
+"#));
+}
+
+#[test]
+fn clink() {
+    let mut app = build_app();
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "myapp", Shell::Clink, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(compare(&*string, CLINK));
+}
+
+#[test]
+fn clink_with_possible_values() {
+    let mut app = App::new("my-app")
+        .arg(Arg::with_name("format")
+            .short('f')
+            .possible_values(&["json", "yaml"]))
+        .arg(Arg::with_name("dir").possible_values(&["up", "down"]))
+        .subcommand(App::new("remote").alias("r").subcommand(App::new("add")))
+        .subcommand(App::new("get-url"))
+        .subcommand(App::new("get_url"));
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "my-app", Shell::Clink, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(string.starts_with("local m = {}\nm[\"my-app remote add\"] = clink.argmatcher()\n"));
+    assert!(!string.contains("local my"));
+    assert!(string.contains("\"add\" .. m[\"my-app remote add\"],\n"));
+    assert!(string.contains("\"r\" .. m[\"my-app remote\"],\n"));
+    assert!(string.contains("\"get-url\" .. m[\"my-app get-url\"],\n"));
+    assert!(string.contains("\"get_url\" .. m[\"my-app get_url\"],\n"));
+    assert!(string.contains("\"help\" .. m[\"my-app help\"],\n        \"up\",\n        \"down\",\n    })"));
+    assert!(string.contains("\"-f\" .. clink.argmatcher():addarg({ \"json\", \"yaml\" }),\n"));
+}
+
 #[test]
 fn elvish_with_special_commands() {
     let mut app = build_app_special_commands();
@@ -883,3 +1788,710 @@ fn zsh_with_special_help() {
 
     assert!(compare(&*string, ZSH_SPECIAL_HELP));
 }
+
+// A third party generator which lists every subcommand path, one per line
+struct Paths;
+
+impl Generator for Paths {
+    fn file_name(name: &str) -> String { format!("{}.paths", name) }
+
+    fn generate(cmd: &CommandSpec, buf: &mut dyn Write) -> Result<(), Error> {
+        for sc in cmd.all_commands() {
+            buf.write_all(format!("{}\n", sc.bin_name).as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn custom_generator() {
+    let mut app = build_app();
+    let mut buf = vec![];
+    generate_with::<Paths, _, _>(&mut app, "myapp", &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+
+    assert_eq!(Paths::file_name("myapp"), "myapp.paths");
+    assert!(compare(&*string, "myapp\nmyapp test\nmyapp help\n"));
+}
+
+#[test]
+fn command_spec_from_app() {
+    let spec = CommandSpec::from_app(&build_app());
+
+    let test = spec.find_subcommand("test").unwrap();
+    assert_eq!(test.bin_name, "myapp test");
+    assert_eq!(test.about.as_ref().unwrap(), "tests things");
+
+    let case = test.find_arg("case").unwrap();
+    assert_eq!(case.kind, ArgKind::Option);
+    assert_eq!(case.long.as_ref().unwrap(), "case");
+    assert_eq!(spec.positionals().count(), 1);
+}
+
+#[test]
+fn json_spec_round_trip() {
+    let spec = command_spec(&mut build_app(), "myapp").unwrap();
+    let json = spec.to_json();
+    assert!(json.contains("\"version\": 1"));
+    assert_eq!(CommandSpec::from_json(&json).unwrap(), spec);
+
+    let mut buf = vec![];
+    generate_completions_from_spec(&CommandSpec::from_json(&json).unwrap(), Shell::Zsh, &mut buf)
+        .unwrap();
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(compare(&*string, ZSH));
+}
+
+#[test]
+fn minimal_json_spec() {
+    let spec = CommandSpec::from_json(
+        r#"{
+            "version": 1,
+            "command": {
+                "name": "deploy",
+                "args": [{ "name": "env", "kind": "option", "long": "env", "possible_values": ["prod", "dev"] }],
+                "subcommands": [{ "name": "rollback", "about": "Undo the last deploy" }]
+            }
+        }"#,
+    ).unwrap();
+    assert_eq!(spec.bin_name, "deploy");
+    assert_eq!(spec.subcommands[0].bin_name, "deploy rollback");
+
+    let mut buf = vec![];
+    generate_completions_from_spec(&spec, Shell::Bash, &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(string.contains("COMPREPLY=($(compgen -W \"prod dev\" -- ${cur}))"));
+    assert!(string.contains("deploy__rollback)"));
+}
+
+#[test]
+fn unsupported_json_spec_version() {
+    match CommandSpec::from_json(r#"{ "version": 2, "command": { "name": "deploy" } }"#) {
+        Err(Error::InvalidSpec(_)) => (),
+        r => panic!("expected Error::InvalidSpec, got {:?}", r),
+    }
+}
+
+#[test]
+fn set_bin_name_renames_subcommands() {
+    let mut spec = command_spec(&mut build_app(), "myapp").unwrap();
+    spec.set_bin_name("mt");
+
+    assert_eq!(spec.bin_name, "mt");
+    assert_eq!(spec.find_subcommand("test").unwrap().bin_name, "mt test");
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn yaml_spec() {
+    let spec = CommandSpec::from_yaml(
+        "version: 1\ncommand:\n  name: deploy\n  subcommands:\n    - { name: rollback }\n",
+    ).unwrap();
+
+    assert_eq!(spec.subcommands[0].bin_name, "deploy rollback");
+}
+
+#[test]
+fn builtin_generator_matches_shell() {
+    let mut buf = vec![];
+    generate_with::<ZshGen, _, _>(&mut build_app(), "myapp", &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(compare(&*string, ZSH));
+}
+
+#[test]
+fn unknown_conflict_is_an_error() {
+    let mut app = build_app().arg(Arg::with_name("quiet").long("quiet").conflicts_with("no-such-arg"));
+    for shell in Shell::variants().iter() {
+        let mut buf = vec![];
+        match try_generate_completions_to(&mut app, "myapp", shell.parse().unwrap(), &mut buf) {
+            Err(Error::MalformedApp(ref msg)) => assert!(msg.contains("'no-such-arg'"), "{}", msg),
+            r => panic!("expected Error::MalformedApp for {}, got {:?}", shell, r),
+        }
+    }
+}
+
+#[test]
+fn missing_bin_name_is_an_error() {
+    let mut spec = command_spec(&mut build_app(), "myapp").unwrap();
+    spec.subcommands[0].bin_name.clear();
+    let mut buf = vec![];
+    match generate_completions_from_spec(&spec, Shell::Zsh, &mut buf) {
+        Err(Error::MalformedApp(_)) => (),
+        r => panic!("expected Error::MalformedApp, got {:?}", r),
+    }
+}
+
+#[test]
+fn unsupported_shell_is_an_error() {
+    let mut buf = vec![];
+    match try_generate_completions_to(&mut build_app(), "myapp", Shell::__Nonexhaustive, &mut buf) {
+        Err(Error::UnsupportedShell) => (),
+        r => panic!("expected Error::UnsupportedShell, got {:?}", r),
+    }
+}
+
+#[test]
+fn io_failure_is_an_error() {
+    match try_gen_completions(&mut build_app().bin_name("myapp"), "myapp", Shell::Bash, "/nonexistent/dir") {
+        Err(Error::Io(_)) => (),
+        r => panic!("expected Error::Io, got {:?}", r),
+    }
+}
+
+// Gets a directory of its own for a test writing files, suffixed by the process id so that
+// concurrent test runs don't write to the same one
+fn temp_out_dir(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{}_{}", name, std::process::id()))
+}
+
+#[test]
+fn gen_all_writes_manifest() {
+    let out_dir = temp_out_dir("clap_generate_gen_all");
+    let opts = GenOptions::new().manifest_file("manifest.json");
+    let manifest = gen_all(&mut build_app(), "myapp", &out_dir, &opts).unwrap();
+
+    let names: Vec<_> = manifest.entries.iter().map(|e| e.output.name()).collect();
+    assert_eq!(
+        names,
+        [
+            "zsh", "bash", "fish", "powershell", "elvish", "nushell", "tcsh", "xonsh", "clink",
+            "man", "man", "man",
+        ]
+    );
+    assert!(manifest.entries[10].path.ends_with("myapp-test.1"));
+    for entry in &manifest.entries {
+        assert!(entry.path.exists(), "{} wasn't written", entry.path.display());
+    }
+    assert_eq!(
+        manifest.entries[1].install_path.as_ref().unwrap(),
+        &std::path::PathBuf::from("share/bash-completion/completions/myapp")
+    );
+
+    let json = std::fs::read_to_string(out_dir.join("manifest.json")).unwrap();
+    assert!(json.contains("\"format\": \"zsh\""));
+    assert!(json.contains("\"install_path\": \"share/zsh/site-functions/_myapp\""));
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[test]
+fn gen_all_writes_fig_and_carapace_specs() {
+    let out_dir = temp_out_dir("clap_generate_specs");
+    let opts = GenOptions::new().shells(&[]).man(false).fig(true).carapace(true);
+    let manifest = gen_all(&mut build_app(), "myapp", &out_dir, &opts).unwrap();
+
+    let names: Vec<_> = manifest.entries.iter().map(|e| e.output.name()).collect();
+    assert_eq!(names, ["fig", "carapace"]);
+    assert!(manifest.entries.iter().all(|e| e.install_path.is_none()));
+    let string = std::fs::read_to_string(out_dir.join("myapp.ts")).unwrap();
+    assert!(compare(&*string, FIG));
+    let string = std::fs::read_to_string(out_dir.join("myapp.yaml")).unwrap();
+    assert!(compare(&*string, CARAPACE));
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[test]
+fn gen_completions_file_named_after_bin_name() {
+    let out_dir = temp_out_dir("clap_generate_bin_name");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let path = try_gen_completions(&mut build_app(), "other", Shell::Bash, &out_dir).unwrap();
+
+    assert_eq!(path, out_dir.join("other.bash"));
+    let string = std::fs::read_to_string(path).unwrap();
+    assert!(string.contains("complete -F _other -o bashdefault -o default other\n"));
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[test]
+fn gen_all_with_file_names_and_bin_aliases() {
+    let out_dir = temp_out_dir("clap_generate_bin_aliases");
+    let opts = GenOptions::new()
+        .man(false)
+        .file_name(Shell::Bash, "myapp")
+        .bin_alias("mt");
+    let manifest = gen_all(&mut build_app(), "myapp", &out_dir, &opts).unwrap();
+    let read = |shell: Shell| {
+        let entry = manifest.entries.iter().find(|e| match e.output {
+            Output::Completions(s) => s == shell,
+            _ => false,
+        });
+        std::fs::read_to_string(&entry.unwrap().path).unwrap()
+    };
+
+    assert_eq!(manifest.entries[1].path, out_dir.join("myapp"));
+    assert!(read(Shell::Bash).contains("complete -F _myapp -o bashdefault -o default myapp mt\n"));
+    assert!(read(Shell::Zsh).starts_with("#compdef myapp mt\n"));
+    assert!(read(Shell::Fish).contains("complete -c mt -w myapp\n"));
+    assert!(read(Shell::PowerShell).contains("-CommandName 'myapp', 'mt'"));
+    assert!(read(Shell::Elvish)
+        .contains("edit:completion:arg-completer[mt] = $edit:completion:arg-completer[myapp]\n"));
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
+
+fn dynamic_values(words: &[&str]) -> Vec<String> {
+    let completer = DynamicCompleter::from_app(&mut build_app(), "myapp")
+        .unwrap()
+        .values_for("test", "case", |_| {
+            vec![Candidate::new("first").help("the first case"), Candidate::new("second")]
+        });
+    completer
+        .complete(words, words.len() - 1)
+        .into_iter()
+        .map(|c| c.value)
+        .collect()
+}
+
+#[test]
+fn dynamic_subcommands_and_switches() {
+    assert_eq!(dynamic_values(&["myapp", ""]), ["test", "help"]);
+    assert_eq!(dynamic_values(&["myapp", "t"]), ["test"]);
+    assert_eq!(dynamic_values(&["myapp", "test", "--c"]), ["--case"]);
+    assert_eq!(dynamic_values(&["myapp", "test", "-"]), ["--case", "--help", "-h", "--version", "-V"]);
+}
+
+#[test]
+fn dynamic_values_for_args() {
+    assert_eq!(dynamic_values(&["myapp", "test", "--case", ""]), ["first", "second"]);
+    assert_eq!(dynamic_values(&["myapp", "test", "--case", "s"]), ["second"]);
+    assert_eq!(dynamic_values(&["myapp", "test", "--case=f"]), ["--case=first"]);
+    assert!(dynamic_values(&["myapp", "test", "--case", "first", ""]).is_empty());
+}
+
+#[test]
+fn dynamic_values_split_by_bash() {
+    assert_eq!(dynamic_values(&["myapp", "test", "--case", "=", "f"]), ["--case=first"]);
+    assert_eq!(dynamic_values(&["myapp", "test", "--case", "="]), ["--case=first", "--case=second"]);
+    assert!(dynamic_values(&["myapp", "test", "--case", "=", "first", ""]).is_empty());
+}
+
+#[test]
+fn dynamic_shims() {
+    for &shell in &Shell::all() {
+        let mut buf = vec![];
+        generate_dynamic_shim("myapp", shell, &mut buf).unwrap();
+        let string = String::from_utf8(buf).unwrap();
+        assert!(string.contains(COMPLETE_ENV), "{} shim doesn't call back", shell);
+    }
+}
+
+#[test]
+fn dynamic_shims_keep_candidates_intact() {
+    let mut buf = vec![];
+    generate_dynamic_shim("myapp", Shell::Bash, &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+    assert!(string.contains("while IFS= read -r candidate; do\n"));
+    assert!(!string.contains("for candidate in $("));
+
+    let mut buf = vec![];
+    generate_dynamic_shim("myapp", Shell::Nushell, &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+    assert!(string.contains("^myapp -- ...$words"));
+    assert!(string.contains("description: $parts.1?"));
+}
+
+fn generate_headings(shell: Shell) -> String {
+    let mut buf = vec![];
+    generate_completions_to(&mut build_app_with_headings(), "my_app", shell, &mut buf);
+    String::from_utf8(buf).unwrap()
+}
+
+#[test]
+fn help_headings_included() {
+    for shell in &[Shell::Bash, Shell::Zsh, Shell::PowerShell, Shell::Elvish] {
+        let string = generate_headings(*shell);
+        assert!(string.contains("--port"), "{} is missing --port", shell);
+        assert!(string.contains("-6"), "{} is missing -6", shell);
+    }
+    let string = generate_headings(Shell::Fish);
+    assert!(string.contains("-l port"), "FISH is missing --port");
+    assert!(string.contains("-s 6"), "FISH is missing -6");
+}
+
+#[test]
+fn zsh_with_help_headings() {
+    let string = generate_headings(Shell::Zsh);
+
+    assert!(compare(&*string, ZSH_HEADINGS));
+}
+
+#[test]
+fn fish_with_help_headings() {
+    let string = generate_headings(Shell::Fish);
+
+    assert!(compare(&*string, FISH_HEADINGS));
+}
+
+#[test]
+fn powershell_with_help_headings() {
+    let string = generate_headings(Shell::PowerShell);
+
+    assert!(compare(&*string, POWERSHELL_HEADINGS));
+}
+
+fn build_app_with_value_hints() -> App<'static, 'static> {
+    App::new("myapp")
+        .arg(Arg::with_name("out").long("output-dir").value_name("DIR").help("where to write to"))
+        .arg(Arg::with_name("host").long("host").value_name("HOST").help("the host to connect to"))
+        .subcommand(App::new("run").arg(Arg::with_name("command").long("exec").value_name("CMD")))
+}
+
+fn generate_value_hints(shell: Shell) -> String {
+    let mut buf = vec![];
+    generate_completions_to(&mut build_app_with_value_hints(), "myapp", shell, &mut buf);
+    String::from_utf8(buf).unwrap()
+}
+
+#[test]
+fn value_hint_from_value_name() {
+    assert_eq!(ValueHint::from_value_name("DIR"), ValueHint::DirPath);
+    assert_eq!(ValueHint::from_value_name("out-file"), ValueHint::FilePath);
+    assert_eq!(ValueHint::from_value_name("REMOTE_HOST"), ValueHint::Hostname);
+    assert_eq!(ValueHint::from_value_name("NUM"), ValueHint::Unknown);
+}
+
+#[test]
+fn value_hints_in_generators() {
+    let bash = generate_value_hints(Shell::Bash);
+    assert!(bash.contains("COMPREPLY=($(compgen -d ${cur}))"));
+    assert!(bash.contains(
+        "--host)\n                        compopt +o default +o bashdefault 2>/dev/null\n                        \
+         COMPREPLY=($(compgen -A hostname ${cur}))"
+    ));
+    assert!(bash.contains("COMPREPLY=($(compgen -c ${cur}))"));
+
+    let zsh = generate_value_hints(Shell::Zsh);
+    assert!(zsh.contains("'--output-dir=[where to write to]: :_path_files -/'"));
+    assert!(zsh.contains("'--host=[the host to connect to]: :_hosts'"));
+
+    let fish = generate_value_hints(Shell::Fish);
+    assert!(fish.contains("-l output-dir -d 'where to write to' -x -a \"(__fish_complete_directories)\""));
+    assert!(fish.contains("-l host -d 'the host to connect to' -x -a \"(__fish_print_hostnames)\""));
+
+    let powershell = generate_value_hints(Shell::PowerShell);
+    assert!(powershell.contains("'myapp;--output-dir' { return Get-ChildItem -Directory"));
+    assert!(powershell.contains("'myapp;run;--exec' { return Get-Command"));
+    assert!(powershell.contains("'myapp;--host' { return @() }"));
+
+    let elvish = generate_value_hints(Shell::Elvish);
+    assert!(elvish.contains("&'myapp;--output-dir'= { edit:complete-filename $words[-1] }"));
+    assert!(!elvish.contains("&'myapp;--host'="));
+
+    let tcsh = generate_value_hints(Shell::Tcsh);
+    assert!(tcsh.contains("'n/--output-dir/d/'"));
+    assert!(tcsh.contains("'n/--exec/c/'"));
+    assert!(tcsh.contains("'n/--host/$hosts/'"));
+    assert!(tcsh.contains("'n/run/(--help -h --version -V --exec)/'"));
+    assert!(tcsh.contains("'c/--/(help version output-dir host)/'"));
+}
+
+#[test]
+fn value_hints_set_explicitly() {
+    let mut spec = command_spec(&mut build_app(), "myapp").unwrap();
+    assert!(spec.set_value_hint("test", "nope", ValueHint::Url).is_err());
+    assert!(spec.set_value_hint("nope", "case", ValueHint::Url).is_err());
+    spec.set_value_hint("test", "case", ValueHint::Url).unwrap();
+
+    let mut buf = vec![];
+    generate_completions_from_spec(&spec, Shell::Zsh, &mut buf).unwrap();
+    assert!(String::from_utf8(buf).unwrap().contains(": :_urls'"));
+
+    spec.set_value_hint("test", "case", ValueHint::ExecutablePath).unwrap();
+    let mut buf = vec![];
+    generate_completions_from_spec(&spec, Shell::Zsh, &mut buf).unwrap();
+    assert!(String::from_utf8(buf).unwrap().contains(": :_files -g \"*(-*)\"'"));
+}
+
+#[test]
+fn man_pages_for_subcommands() {
+    let app = build_app()
+        .arg(Arg::with_name("verbose")
+            .long("verbose")
+            .global(true)
+            .help("more output"))
+        .subcommand(App::new("remote")
+            .about("manages remotes")
+            .subcommand(App::new("add").about("adds a remote")));
+    let manuals: Vec<String> = gen_manuals(&app).into_iter().map(|m| m.render()).collect();
+
+    assert_eq!(manuals.len(), 4);
+    assert!(manuals[0].contains("myapp-test"));
+    assert!(manuals[0].contains("myapp-remote"));
+    assert!(manuals[1].contains("myapp-test"));
+    assert!(manuals[1].contains("--case"));
+    assert!(manuals[1].contains("--verbose"), "global arg missing from subcommand page");
+    assert!(manuals[1].contains("\\fBmyapp\\fR(1)"));
+    assert!(manuals[3].contains("myapp-remote-add"));
+    assert!(manuals[3].contains("--verbose"), "global arg missing from nested page");
+    assert!(manuals[3].contains("\\fBmyapp-remote\\fR(1)"));
+}
+
+#[test]
+fn man_page_value_details_and_environment() {
+    let app = App::new("myapp")
+        .arg(Arg::with_name("file").help("some input file").required(true))
+        .arg(Arg::with_name("color")
+            .long("color")
+            .value_name("WHEN")
+            .possible_values(&["auto", "always", "never"])
+            .default_value("auto")
+            .env("MYAPP_COLOR")
+            .help("when to color"))
+        .arg(Arg::with_name("token").long("token").required(true).env("MYAPP_TOKEN"));
+    let manual = gen_manuals(&app).remove(0).render();
+
+    assert!(manual.contains("<WHEN>"));
+    assert!(manual.contains(
+        "when to color [possible values: auto, always, never] [default: auto] [env: MYAPP_COLOR]"
+    ));
+    assert!(manual.contains("[env: MYAPP_TOKEN] [required]"));
+    assert!(manual.contains("some input file"));
+    assert!(manual.contains("Sets --color if not given. when to color"));
+    assert!(manual.contains("Sets --token if not given."));
+}
+
+#[test]
+fn man_page_options_and_sections() {
+    let app = App::new("myapp")
+        .version("1.2.3")
+        .long_about("Does many things")
+        .after_help("Report bugs to the tracker")
+        .subcommand(App::new("test"));
+    let options = ManualOptions::new()
+        .man_section("8")
+        .date("2018-06-01")
+        .example("Run the tests", "myapp test")
+        .section("exit status", &["0 on success"]);
+    let pages = gen_manuals_with(&app, &options);
+
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[1].file_name(), "myapp-test.8");
+    assert!(pages[0]
+        .content
+        .starts_with(".TH \"MYAPP\" \"8\" \"2018-06-01\" \"myapp 1.2.3\" \"User Commands\"\n"));
+    assert!(pages[1].content.starts_with(".TH \"MYAPP-TEST\" \"8\""));
+    assert!(pages[0].content.contains("Does many things"));
+    assert!(pages[0].content.contains("Report bugs to the tracker"));
+    assert!(pages[0].content.contains("myapp test"));
+    assert!(pages[0].content.contains("0 on success"));
+    assert!(!pages[1].content.contains("0 on success"));
+    assert!(pages[0].content.contains("See \\fBmyapp-test\\fR(8)."));
+    assert!(pages[1].content.contains("\\fBmyapp\\fR(8)"));
+    assert!(!pages[0].content.contains("(1)") && !pages[1].content.contains("(1)"));
+}
+
+fn build_app_with_hidden() -> App<'static, 'static> {
+    App::new("myapp")
+        .arg(Arg::with_name("verbose").long("verbose").conflicts_with("debug-dump"))
+        .arg(Arg::with_name("debug-dump").long("debug-dump").hidden(true))
+        .subcommand(App::new("test"))
+        .subcommand(App::new("internal").setting(clap::AppSettings::Hidden))
+}
+
+#[test]
+fn hidden_args_and_subcommands() {
+    for &shell in &[Shell::Bash, Shell::Zsh, Shell::Fish] {
+        let mut buf = vec![];
+        generate_completions_to(&mut build_app_with_hidden(), "myapp", shell, &mut buf);
+        let string = String::from_utf8(buf).unwrap();
+
+        assert!(string.contains("verbose"), "{}", shell);
+        assert!(string.contains("test"), "{}", shell);
+        assert!(!string.contains("debug-dump"), "{}", shell);
+        assert!(!string.contains("internal"), "{}", shell);
+    }
+
+    let mut pages = gen_manuals(&build_app_with_hidden());
+    assert_eq!(pages.len(), 2);
+    let manual = pages.remove(0).render();
+    assert!(!manual.contains("dump"));
+    assert!(!manual.contains("internal"));
+}
+
+#[test]
+fn include_hidden_args_and_subcommands() {
+    let mut spec = command_spec(&mut build_app_with_hidden(), "myapp").unwrap();
+    spec.unhide();
+    let mut buf = vec![];
+    generate_completions_from_spec(&spec, Shell::Bash, &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(string.contains("debug-dump"));
+    assert!(string.contains("internal"));
+    let pages = gen_manuals_from_spec_with(&spec, &ManualOptions::new());
+    assert!(pages.iter().any(|p| p.file_name() == "myapp-internal.1"));
+}
+
+#[test]
+fn fish_nested_subcommands() {
+    let mut app = App::new("myapp")
+        .arg(Arg::with_name("config").short('c').long("config").takes_value(true))
+        .subcommand(
+            App::new("remote")
+                .visible_alias("rmt")
+                .subcommand(App::new("add").arg(Arg::with_name("url").long("url"))),
+        )
+        .subcommand(
+            App::new("config").subcommand(App::new("add").arg(Arg::with_name("key").long("key"))),
+        );
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "myapp", Shell::Fish, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(string.contains("case '*;--'\n                break\n"));
+    assert!(string.contains("case ';--config' ';-c'\n                set skip 1\n"));
+    assert!(string.contains("case ';remote' ';rmt'\n                set path 'remote'\n"));
+    assert!(string.contains("case 'config;add'\n                set path 'config add'\n"));
+    assert!(string.contains("-n \"__fish_myapp_using_subcommand\" -f -a \"rmt\""));
+    assert!(string.contains("-n \"__fish_myapp_using_subcommand remote add; and not"));
+    assert!(string.contains("-n \"__fish_myapp_using_subcommand config add; and not"));
+    assert!(!string.contains("__fish_seen_subcommand_from"));
+}
+
+#[test]
+fn fish_value_semantics() {
+    let mut app = App::new("myapp")
+        .arg(Arg::with_name("config").short('c').long("config").takes_value(true))
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .possible_values(&["auto", "never"])
+                .conflicts_with("quiet"),
+        )
+        .arg(Arg::with_name("quiet").short('q').long("quiet"))
+        .arg(Arg::with_name("verbose").short('v').multiple_occurrences(true))
+        .arg(Arg::with_name("mode").possible_values(&["fast", "slow"]));
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "myapp", Shell::Fish, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+    let mut buf = vec![];
+    let mut app_with_file = App::new("myapp")
+        .arg(Arg::with_name("mode").possible_values(&["fast", "it's \"slow\""]))
+        .arg(Arg::with_name("input"));
+    generate_completions_to(&mut app_with_file, "myapp", Shell::Fish, &mut buf);
+    let with_file = String::from_utf8(buf).unwrap();
+
+    assert!(string.contains("-n \"not __fish_contains_opt -s c config\" -s c -l config -r\n"));
+    // Conflicts go both ways
+    assert!(string.contains(
+        "-n \"not __fish_contains_opt -s q quiet color\" -l color -x -a \"auto never\"\n"
+    ));
+    assert!(string.contains("-n \"not __fish_contains_opt -s q quiet color\" -s q -l quiet -f\n"));
+    assert!(string.contains("complete -c myapp -s v -f\n"));
+    assert!(string.contains("complete -c myapp -f -a \"fast slow\"\n"));
+    // The other positional still completes files, and each value is a single word
+    assert!(with_file.contains("complete -c myapp -a \"fast 'it\\\\'s \\\"slow\\\"'\"\n"));
+}
+
+#[test]
+fn bash_tracks_exact_subcommand_path() {
+    let mut app = App::new("myapp")
+        .arg(Arg::with_name("config").short('c').long("config").takes_value(true))
+        .subcommand(App::new("build"))
+        .subcommand(
+            App::new("remote")
+                .alias("rem")
+                .subcommand(App::new("add").subcommand(App::new("build"))),
+        );
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "myapp", Shell::Bash, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(string.contains("myapp,build)\n                cmd=\"myapp__build\"\n"));
+    assert!(string.contains(
+        "myapp__remote__add,build)\n                cmd=\"myapp__remote__add__build\"\n"
+    ));
+    assert!(string.contains("myapp,remote|myapp,rem)\n                cmd=\"myapp__remote\"\n"));
+    // The value of --config is never taken for a subcommand
+    assert!(string.contains("myapp,--config|myapp,-c)\n"));
+    assert!(!string.contains("cmd+="));
+}
+
+#[test]
+fn bash_attached_values() {
+    let mut app = App::new("myapp")
+        .arg(Arg::with_name("output").short('o').long("output").takes_value(true))
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .possible_values(&["auto", "never"])
+                .require_equals(true),
+        );
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "myapp", Shell::Bash, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(string.contains("_get_comp_words_by_ref -n =: cur prev words cword\n"));
+    assert!(string.contains("    if [[ ${cur} == --*=* ]] ; then\n        prev=\"${cur%%=*}\"\n"));
+    assert!(string.contains("                -o?*)\n                    prev=\"${cur:0:2}\"\n"));
+    assert!(string.contains(" --output --color= "));
+    assert!(string.contains("myapp,--output|myapp,-o)\n"));
+    assert!(!string.contains("myapp,--color"));
+    assert!(string.contains("__ltrim_colon_completions \"${prefix}${cur}\"\n"));
+    assert!(string.contains("compopt -o nospace"));
+}
+
+#[test]
+fn bash_positional_values_and_used_options() {
+    let mut app = App::new("myapp")
+        .arg(Arg::with_name("config").short('c').long("config").takes_value(true))
+        .arg(Arg::with_name("verbose").short('v').multiple_occurrences(true))
+        .arg(Arg::with_name("mode").possible_values(&["fast", "slow"]))
+        .arg(Arg::with_name("file"));
+    let mut buf = vec![];
+    generate_completions_to(&mut app, "myapp", Shell::Bash, &mut buf);
+    let string = String::from_utf8(buf).unwrap();
+
+    assert!(string.contains("opts=\" -c -v -h -V --config --help --version \""));
+    assert!(!string.contains("<mode>"));
+    assert!(string.contains("0)\n                                COMPREPLY+=($(compgen -W \"fast slow\""));
+    assert!(string.contains("1)\n                                COMPREPLY+=($(compgen -f ${cur}))\n"));
+    assert!(string.contains("-c|--config)\n                        opts=\"${opts/ -c / }\"\n"));
+    // -v may be given more than once
+    assert!(!string.contains("-v)\n"));
+}
+
+#[test]
+fn group_and_global_conflicts() {
+    let mut app = App::new("myapp")
+        .arg(Arg::with_name("json").long("json").group("format"))
+        .arg(Arg::with_name("yaml").long("yaml").group("format"))
+        .arg(Arg::with_name("raw").long("raw").conflicts_with("format"))
+        .arg(Arg::with_name("no-color").long("no-color"))
+        .arg(Arg::with_name("color").long("color").global(true).conflicts_with("no-color"))
+        .subcommand(App::new("test"));
+    let spec = command_spec(&mut app, "myapp").unwrap();
+    assert_eq!(spec.find_arg("raw").unwrap().conflicts, vec!["json", "yaml"]);
+
+    for shell in Shell::variants().iter() {
+        let mut buf = vec![];
+        try_generate_completions_to(&mut app, "myapp", shell.parse().unwrap(), &mut buf).unwrap();
+    }
+    gen_manuals_from_spec(&spec);
+
+    let mut buf = vec![];
+    generate_completions_from_spec(&spec, Shell::Zsh, &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+    assert!(string.contains("'(--json --yaml)--raw[]' \\\n"));
+    assert!(string.contains("'(--no-color)--color[]' \\\n"));
+
+    let mut buf = vec![];
+    generate_from_spec_with::<FigGen, _>(&spec, &mut buf).unwrap();
+    let string = String::from_utf8(buf).unwrap();
+    assert!(string.contains(
+        r#""name": "--raw",
+      "exclusiveOn": [
+        "--json",
+        "--yaml"
+      ]"#
+    ));
+    assert!(string.contains(
+        r#""name": "--color",
+      "isPersistent": true,
+      "exclusiveOn": [
+        "--no-color"
+      ]"#
+    ));
+}